@@ -0,0 +1,129 @@
+//! Benchmarks of the dispatch path: `log::logger()` -> `LogImpl` -> `HandlerCompound` -> `HandlerImpl` -> appenders.
+//! See `benches/README.md` for how to run them and the baseline.
+use std::hint::black_box;
+use std::thread;
+use std::time::{Instant, SystemTime};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use log::{Level, LevelFilter, Record};
+use trivial_log::{AppenderBuilder, DefaultFormatOptions};
+
+/// Format that only copies the arguments, cheaper than the default format.
+#[expect(clippy::unnecessary_wraps, reason = "signature required by Builder::format")]
+fn plain(_: SystemTime, record: &Record<'_>) -> Option<String> {
+  Some(record.args().to_string())
+}
+
+/// Registers `count` distinct appenders that do nothing, so only the dispatch and the format are measured, for Info and more severe.
+fn appenders(count: usize) -> impl FnOnce(AppenderBuilder<String>) -> AppenderBuilder<String> {
+  move |mut builder| {
+    for _ in 0..count {
+      builder = builder.appender_filter(LevelFilter::Info, |msg: &String| {
+        black_box(msg);
+      });
+    }
+    builder
+  }
+}
+
+/// Logs a record through the installed logger.
+fn log_info() {
+  log::logger()
+    .log(&Record::builder().level(Level::Info).args(format_args!("bench {}", 1)).build());
+}
+
+/// Records below the max level are rejected by the `log` macros, the logger is never called.
+/// Records at a level without appenders pass the max level check but are dropped by `HandlerCompound`.
+fn disabled(c: &mut Criterion) {
+  trivial_log::builder().format(plain, appenders(1)).init().expect("init");
+
+  let mut group = c.benchmark_group("disabled");
+  group.bench_function("macro_below_max_level", |b| b.iter(|| log::debug!("bench {}", 1)));
+  group.bench_function("logger_no_appender", |b| {
+    b.iter(|| {
+      log::logger()
+        .log(&Record::builder().level(Level::Debug).args(format_args!("bench {}", 1)).build());
+    });
+  });
+  trivial_log::free();
+  group.finish();
+}
+
+/// One, two and eight handlers (formats) with one appender each.
+fn handlers(c: &mut Criterion) {
+  let mut group = c.benchmark_group("handlers");
+  for count in [1, 2, 8] {
+    let mut builder = trivial_log::builder();
+    for _ in 0..count {
+      builder = builder.format(plain, appenders(1));
+    }
+    builder.init().expect("init");
+
+    group.bench_function(count.to_string(), |b| b.iter(log_info));
+    trivial_log::free();
+  }
+  group.finish();
+}
+
+/// One handler (format) with one, eight and 32 appenders.
+fn appenders_per_format(c: &mut Criterion) {
+  let mut group = c.benchmark_group("appenders_per_format");
+  for count in [1, 8, 32] {
+    trivial_log::builder().format(plain, appenders(count)).init().expect("init");
+    group.bench_function(count.to_string(), |b| b.iter(log_info));
+    trivial_log::free();
+  }
+  group.finish();
+}
+
+/// Several threads log concurrently through the `RwLock` read path, the time is the wall time per record.
+fn contention(c: &mut Criterion) {
+  trivial_log::builder().format(plain, appenders(1)).init().expect("init");
+
+  let mut group = c.benchmark_group("contention");
+  for threads in [1_u32, 2, 4, 8] {
+    group.bench_function(format!("{threads}_threads"), |b| {
+      b.iter_custom(|iters| {
+        let per_thread = iters.div_ceil(u64::from(threads));
+        let start = Instant::now();
+        thread::scope(|scope| {
+          for _ in 0..threads {
+            scope.spawn(|| {
+              for _ in 0..per_thread {
+                log_info();
+              }
+            });
+          }
+        });
+        // Wall time for all records, criterion divides it by `iters`.
+        start.elapsed()
+      });
+    });
+  }
+  trivial_log::free();
+  group.finish();
+}
+
+/// Cost of the default format itself, through the logger and without it.
+fn default_format(c: &mut Criterion) {
+  let now = SystemTime::now();
+  let record =
+    Record::builder().level(Level::Info).target("bench").args(format_args!("bench 1")).build();
+  let options = DefaultFormatOptions::new().target(true).location(true);
+
+  let mut group = c.benchmark_group("default_format");
+  group.bench_function("format", |b| {
+    b.iter(|| black_box(DefaultFormatOptions::new().format(now, &record)));
+  });
+  group.bench_function("format_with_target_and_location", |b| {
+    b.iter(|| black_box(options.format(now, &record)));
+  });
+
+  trivial_log::builder().default_format(appenders(1)).init().expect("init");
+  group.bench_function("logger", |b| b.iter(log_info));
+  trivial_log::free();
+  group.finish();
+}
+
+criterion_group!(benches, disabled, handlers, appenders_per_format, contention, default_format);
+criterion_main!(benches);
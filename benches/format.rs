@@ -0,0 +1,30 @@
+//! Compares the owned `DefaultFormatOptions::format` with `format_into` and a reused buffer.
+use std::hint::black_box;
+use std::time::SystemTime;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use log::{Level, Record};
+use trivial_log::DefaultFormatOptions;
+
+/// Formats the same record once per iteration.
+fn format(c: &mut Criterion) {
+  let options = DefaultFormatOptions::new();
+  let now = SystemTime::now();
+  let args = format_args!("request {} finished in {}ms", 42, 7);
+  let record = Record::builder().level(Level::Info).target("bench").args(args).build();
+
+  let mut group = c.benchmark_group("default_format");
+  group.bench_function("format", |b| b.iter(|| black_box(options.format(now, &record))));
+  group.bench_function("format_into", |b| {
+    let mut buf = String::new();
+    b.iter(|| {
+      buf.clear();
+      _ = options.format_into(now, &record, &mut buf);
+      black_box(buf.len())
+    });
+  });
+  group.finish();
+}
+
+criterion_group!(benches, format);
+criterion_main!(benches);
@@ -1,6 +1,8 @@
 use log::{debug, error, info, Level};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::time::SystemTime;
+use trivial_log::context;
 
 #[derive(Serialize, Deserialize)]
 struct LogEntity {
@@ -8,6 +10,8 @@ struct LogEntity {
   level: String,
   thread: String,
   timestamp: u128,
+  #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+  context: BTreeMap<String, String>,
 }
 
 fn main() {
@@ -25,6 +29,13 @@ fn main() {
           level: record.level().to_string(),
           thread: format!("{:?}", std::thread::current().id()),
           timestamp: now.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_millis(),
+          context: {
+            let mut context = BTreeMap::new();
+            context::for_each(|key, value| {
+              context.insert(key.to_string(), value.to_string());
+            });
+            context
+          },
         })
       },
       |builder| {
@@ -48,7 +59,7 @@ fn main() {
   println!("normal println");
   debug!("warning");
   let t = std::thread::spawn(move || {
-    info!("猫");
+    context::scope(&[("request_id", "abc"), ("user_id", "7")], || info!("猫"));
   });
   t.join().unwrap();
 
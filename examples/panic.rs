@@ -0,0 +1,40 @@
+#![expect(clippy::unwrap_used)]
+
+use std::sync::Mutex;
+
+use log::{info, Level, LevelFilter};
+use trivial_log::StdoutAppender;
+
+/// The error messages, the panic is logged as one.
+static ERRORS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn main() {
+  trivial_log::builder()
+    .default_format(|builder| {
+      builder
+        .appender_filter(LevelFilter::Trace, StdoutAppender::new())
+        .appender(Level::Error, |msg: &String| ERRORS.lock().unwrap().push(msg.clone()))
+    })
+    .init()
+    .unwrap();
+  trivial_log::install_panic_hook();
+
+  info!("starting worker");
+  let worker = std::thread::Builder::new()
+    .name("worker".to_string())
+    .spawn(|| panic!("worker failed"))
+    .unwrap();
+  assert!(worker.join().is_err());
+
+  trivial_log::free();
+
+  let errors = std::mem::take(&mut *ERRORS.lock().unwrap());
+  assert_eq!(errors.len(), 1);
+  assert!(errors.iter().all(|msg| msg.contains("thread 'worker' panicked at")), "{errors:?}");
+  assert!(errors.iter().all(|msg| msg.contains("worker failed")), "{errors:?}");
+}
+
+#[test]
+fn run() {
+  main();
+}
@@ -0,0 +1,42 @@
+#![expect(clippy::unwrap_used)]
+
+use log::{debug, error, info, trace, LevelFilter};
+use std::fs;
+use trivial_log::RingBufferAppender;
+
+fn main() {
+  let dump = std::env::temp_dir().join("trivial_log_ring_buffer_example.log");
+  _ = fs::remove_file(&dump);
+
+  trivial_log::builder()
+    .default_format(|builder| {
+      builder
+        .appender_filter(LevelFilter::Info, |msg: &String| print!("{msg}"))
+        // Everything is kept in memory and only written once an error occurs.
+        .appender_filter(
+          LevelFilter::Trace,
+          RingBufferAppender::new(100).dump_on_error(|msg: &String| print!("CONTEXT: {msg}")),
+        )
+    })
+    .dump_on_panic(&dump)
+    .init()
+    .unwrap();
+
+  trace!("connecting");
+  debug!("connected");
+  info!("request received");
+  error!("request failed");
+
+  debug!("retrying");
+  let t = std::thread::spawn(|| panic!("giving up"));
+  assert!(t.join().is_err());
+  print!("PANIC DUMP: {}", fs::read_to_string(&dump).unwrap());
+  _ = fs::remove_file(&dump);
+
+  trivial_log::free();
+}
+
+#[test]
+fn run() {
+  main();
+}
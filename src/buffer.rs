@@ -0,0 +1,59 @@
+use std::cell::RefCell;
+use std::fmt;
+
+use log::Record;
+
+use crate::{Formatter, LogContext};
+
+/// Buffers that grew larger than this are released after use, so a single huge message doesn't keep its memory forever.
+const MAX_RETAINED_CAPACITY: usize = 16 * 1024;
+
+thread_local! {
+  /// The reusable buffer of the current thread.
+  static BUFFER: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Releases the buffer of the current thread.
+/// Buffers of other threads are released when the threads exit.
+pub fn release() {
+  _ = BUFFER.try_with(|cell| {
+    if let Ok(mut buf) = cell.try_borrow_mut() {
+      *buf = String::new();
+    }
+  });
+}
+
+/// Formatter for format fns that write into the reusable buffer, like `Builder::format_into` and the default format.
+pub struct FormatInto<F>(pub F);
+
+impl<F> Formatter<String> for FormatInto<F>
+where
+  F: Fn(&LogContext<'_>, &Record<'_>, &mut String) -> fmt::Result + Send + Sync,
+{
+  fn format(&self, ctx: &LogContext<'_>, record: &Record<'_>, append: &mut dyn FnMut(&String)) {
+    let mut format = |buf: &mut String| {
+      if (self.0)(ctx, record, buf).is_ok() {
+        append(buf);
+      }
+    };
+
+    // The buffer is unavailable if an appender logs (it is still borrowed) or the thread is exiting.
+    let reused = BUFFER.try_with(|cell| {
+      let Ok(mut buf) = cell.try_borrow_mut() else {
+        return false;
+      };
+
+      buf.clear();
+      format(&mut buf);
+      if buf.capacity() > MAX_RETAINED_CAPACITY {
+        *buf = String::new();
+      }
+
+      true
+    });
+
+    if reused != Ok(true) {
+      format(&mut String::new());
+    }
+  }
+}
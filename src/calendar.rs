@@ -0,0 +1,206 @@
+use std::fmt;
+
+/// Milliseconds per day
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+/// A date and time in the proleptic Gregorian calendar at a fixed offset to UTC.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct CivilDateTime {
+  /// Year, can be negative
+  pub year: i64,
+  /// Month 1..=12
+  pub month: i64,
+  /// Day of the month 1..=31
+  pub day: i64,
+  /// Hour 0..=23
+  pub hour: i64,
+  /// Minute 0..=59
+  pub minute: i64,
+  /// Second 0..=59
+  pub second: i64,
+  /// Millisecond 0..=999
+  pub millis: i64,
+  /// Offset to UTC in seconds
+  pub offset: i32,
+}
+
+impl CivilDateTime {
+  /// Converts milliseconds since the unix epoch into a date and time at the given offset (in seconds) to UTC.
+  pub const fn from_unix_millis(millis: i64, offset: i32) -> Self {
+    let local = millis.saturating_add(offset as i64 * 1000);
+    let (year, month, day) = civil_from_days(local.div_euclid(MILLIS_PER_DAY));
+    let of_day = local.rem_euclid(MILLIS_PER_DAY);
+
+    Self {
+      year,
+      month,
+      day,
+      hour: of_day / 3_600_000,
+      minute: of_day / 60_000 % 60,
+      second: of_day / 1000 % 60,
+      millis: of_day % 1000,
+      offset,
+    }
+  }
+
+  /// English abbreviation of the month, `Jan`..`Dec`
+  pub const fn month_abbr(&self) -> &'static str {
+    match self.month {
+      1 => "Jan",
+      2 => "Feb",
+      3 => "Mar",
+      4 => "Apr",
+      5 => "May",
+      6 => "Jun",
+      7 => "Jul",
+      8 => "Aug",
+      9 => "Sep",
+      10 => "Oct",
+      11 => "Nov",
+      _ => "Dec",
+    }
+  }
+
+  /// Writes `17 Feb 2025 22:27:20.956` followed by ` UTC` if `utc` or the offset (` +01:00`) otherwise.
+  pub fn write_default(&self, out: &mut impl fmt::Write, utc: bool) -> fmt::Result {
+    write!(
+      out,
+      "{:02} {} {:04} {:02}:{:02}:{:02}.{:03}",
+      self.day,
+      self.month_abbr(),
+      self.year,
+      self.hour,
+      self.minute,
+      self.second,
+      self.millis
+    )?;
+
+    if utc {
+      return out.write_str(" UTC");
+    }
+
+    out.write_char(' ')?;
+    self.write_offset(out)
+  }
+
+  /// Writes `2025-02-17T22:27:20.956` followed by `Z` if `utc` or the offset (`+01:00`) otherwise.
+  pub fn write_rfc3339(&self, out: &mut impl fmt::Write, utc: bool) -> fmt::Result {
+    write!(
+      out,
+      "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}",
+      self.year, self.month, self.day, self.hour, self.minute, self.second, self.millis
+    )?;
+
+    if utc {
+      return out.write_char('Z');
+    }
+
+    self.write_offset(out)
+  }
+
+  /// Writes the offset as `+01:00`
+  pub fn write_offset(&self, out: &mut impl fmt::Write) -> fmt::Result {
+    let sign = if self.offset < 0 { '-' } else { '+' };
+    let offset = self.offset.unsigned_abs();
+    write!(out, "{sign}{:02}:{:02}", offset / 3600, offset / 60 % 60)
+  }
+}
+
+/// Converts days since 1970-01-01 into (year, month, day).
+/// See <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+const fn civil_from_days(days: i64) -> (i64, i64, i64) {
+  let z = days + 719_468;
+  let era = z.div_euclid(146_097);
+  let doe = z.rem_euclid(146_097); // [0, 146096]
+  let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365; // [0, 399]
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100); // [0, 365]
+  let mp = (5 * doy + 2) / 153; // [0, 11]
+  let day = doy - (153 * mp + 2) / 5 + 1; // [1, 31]
+  let month = if mp < 10 { mp + 3 } else { mp - 9 }; // [1, 12]
+  let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+  (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+  use crate::calendar::CivilDateTime;
+
+  fn default(millis: i64, offset: i32) -> String {
+    let mut buf = String::new();
+    CivilDateTime::from_unix_millis(millis, offset)
+      .write_default(&mut buf, offset == 0)
+      .expect("fmt");
+    buf
+  }
+
+  fn rfc3339(millis: i64, offset: i32) -> String {
+    let mut buf = String::new();
+    CivilDateTime::from_unix_millis(millis, offset)
+      .write_rfc3339(&mut buf, offset == 0)
+      .expect("fmt");
+    buf
+  }
+
+  #[test]
+  fn known_dates() {
+    assert_eq!(default(0, 0), "01 Jan 1970 00:00:00.000 UTC");
+    assert_eq!(default(1_739_831_240_956, 0), "17 Feb 2025 22:27:20.956 UTC");
+    assert_eq!(default(1_739_831_240_956, -5400), "17 Feb 2025 20:57:20.956 -01:30");
+    assert_eq!(rfc3339(1_739_831_240_956, 0), "2025-02-17T22:27:20.956Z");
+    assert_eq!(rfc3339(951_782_400_000, 3600), "2000-02-29T01:00:00.000+01:00");
+    assert_eq!(rfc3339(-1, 0), "1969-12-31T23:59:59.999Z");
+  }
+
+  /// Compares random timestamps between year 1 and 9999 against chrono.
+  #[cfg(feature = "chrono")]
+  #[test]
+  fn matches_chrono() {
+    /// 0001-01-01T00:00:00Z
+    const MIN: i64 = -62_135_596_800_000;
+    /// 9999-12-31T23:59:59.999Z
+    const MAX: i64 = 253_402_300_799_999;
+
+    let mut state: u64 = 0x5EED;
+    let mut next = || {
+      state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+      let mut z = state;
+      z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+      z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+      z ^ (z >> 31)
+    };
+
+    let range = u64::try_from(MAX - MIN).expect("positive");
+    for _ in 0..20_000 {
+      let millis = MIN + i64::try_from(next() % range).expect("in range");
+      let offset = i32::try_from(next() % 86_400).expect("in range") - 43_200;
+      let offset = offset - offset % 60;
+
+      let utc = chrono::DateTime::from_timestamp_millis(millis).expect("in range");
+      assert_eq!(
+        default(millis, 0),
+        utc.format("%d %b %Y %H:%M:%S%.3f UTC").to_string(),
+        "{millis}"
+      );
+      assert_eq!(
+        rfc3339(millis, 0),
+        utc.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        "{millis}"
+      );
+
+      if offset != 0 {
+        let tz = chrono::FixedOffset::east_opt(offset).expect("in range");
+        let local = utc.with_timezone(&tz);
+        assert_eq!(
+          default(millis, offset),
+          local.format("%d %b %Y %H:%M:%S%.3f %:z").to_string(),
+          "{millis} {offset}"
+        );
+        assert_eq!(
+          rfc3339(millis, offset),
+          local.to_rfc3339_opts(chrono::SecondsFormat::Millis, false),
+          "{millis} {offset}"
+        );
+      }
+    }
+  }
+}
@@ -0,0 +1,17 @@
+use std::env;
+use std::process::Command;
+
+/// Runs the ignored test `name` in a child process with the env vars and returns its (stdout, stderr).
+///
+/// Tests that log to stdout/stderr, install a panic hook or call `trivial_log::free()` run this way,
+/// so they do not interfere with the other tests. Their entry points are `#[ignore]`d,
+/// so `cargo test` does not count them as passed when they run on their own.
+pub fn run(name: &str, envs: &[(&str, &str)]) -> (String, String) {
+  let output = Command::new(env::current_exe().expect("current exe"))
+    .args([name, "--exact", "--ignored", "--nocapture", "--test-threads=1"])
+    .envs(envs.iter().copied())
+    .output()
+    .expect("spawn child");
+  assert!(output.status.success(), "{output:?}");
+  (String::from_utf8(output.stdout).expect("utf8"), String::from_utf8(output.stderr).expect("utf8"))
+}
@@ -0,0 +1,1154 @@
+//!
+//! The configuration consists of a global level, target directives and any number of named appenders,
+//! each with its own kind, level and format. A dependency free INI-like format is always available,
+//! TOML files can be loaded with the `toml` feature.
+//!
+//!```text
+//! # Level of appenders that don't set one, defaults to info
+//! level = info
+//!
+//! # Overrides the level for targets (module paths) and their children
+//! [targets]
+//! hyper = warn
+//! my_app::db = trace
+//!
+//! [appender.console]
+//! kind = stderr            # stdout, stderr, file, rolling, dated, tcp or syslog
+//! format = default         # default, json, logfmt or pattern
+//! color = auto
+//! thread = name
+//!
+//! [appender.file]
+//! kind = rolling
+//! path = /var/log/my_app.log
+//! max_size = 10M
+//! keep = 5
+//! level = debug
+//! format = pattern
+//! pattern = "{timestamp} {level} [{thread}] {target} - {message}"
+//!```
+//!
+//! Comments start with `#` or `;`, after a value they have to be separated by whitespace.
+//!
+//! Appenders accept these keys:
+//! - `kind` (required), `level`, `format`
+//! - `path` for `file` and `rolling`, `max_size` (bytes, `K`/`M`/`G` suffixes) and `keep` for `rolling`
+//! - `path` for `dated`, a strftime template like `logs/app-%Y-%m-%d.log` expanded in UTC
+//! - `max_total_size` of all archives, `max_age` (`30m`, `12h`, `7d`) and `compress` (requires the `compression`
+//!   feature) for `rolling`, the limits are checked when the file is rotated
+//! - `buffered` for `stdout` and `stderr`
+//! - `address` (`host:port`) for `tcp`, the connection is not reestablished if it breaks
+//! - `path` (default `/dev/log`), `ident` and `facility` for `syslog`, which is only available on unix
+//! - `pattern` for the `pattern` format, with the placeholders `{level}`, `{timestamp}`, `{uptime}`, `{delta}`,
+//!   `{seq}`, `{thread}`, `{tid}` (kernel thread id), `{thread_tid}` (`main(4711)`), `{target}`, `{file}`, `{line}`,
+//!   `{message}` and `{fields}`
+//! - `time_zone` (`utc`, `local`, `+01:00`), `timestamp` (`default`, `rfc3339`, `iso8601`, `epoch_millis`, `uptime`
+//!   or a strftime string), `thread` (`id`, `name`, `os_id`, `name_and_os_id`), `target`, `location`, `sequence`,
+//!   `delta` and `color` (`auto`, `always`, `never`) for the `default` format
+//!
+//!```rust
+//! let config = trivial_log::config::parse("
+//! [appender.console]
+//! kind = stdout
+//! format = logfmt
+//! ").unwrap();
+//! config.init().unwrap();
+//! log::info!("configured");
+//! trivial_log::free();
+//!```
+//!
+//! `ConfigWatcher` applies the file again whenever it changes.
+use std::fs::OpenOptions;
+use std::io;
+use std::io::BufWriter;
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{LevelFilter, Record};
+
+pub use crate::watch::ConfigWatcher;
+
+use crate::formats::{self, Pattern};
+use crate::{
+  buffer, Appender, AppenderBuilder, Builder, ColorChoice, DatedFileAppender, DefaultFormatOptions,
+  Error, IntoAppender, LogContext, RollingFileAppender, StderrAppender, StdoutAppender,
+  ThreadFormat, TimeZone, TimestampFormat,
+};
+
+/// A `key = value` line.
+struct Entry {
+  /// The key
+  key: String,
+  /// The value without quotes
+  value: String,
+  /// Where the entry was read from
+  line: Option<usize>,
+}
+
+/// The entries of a `[section]`, or of the top of the file if the name is empty.
+struct Section {
+  /// The name between the brackets
+  name: String,
+  /// The line of the header
+  line: Option<usize>,
+  /// The entries in the order they were read
+  entries: Vec<Entry>,
+}
+
+/// Removes a comment after the content, a `#` or `;` that follows whitespace outside of quotes.
+fn strip_comment(line: &str) -> &str {
+  let mut quoted = false;
+  let mut previous = ' ';
+  for (idx, c) in line.char_indices() {
+    match c {
+      '"' => quoted = !quoted,
+      '#' | ';' if !quoted && previous.is_whitespace() => return line.get(..idx).unwrap_or(line),
+      _ => {}
+    }
+    previous = c;
+  }
+  line
+}
+
+/// Parses the INI-like format into sections.
+fn parse_ini(text: &str) -> Result<Vec<Section>, Error> {
+  let mut sections = vec![Section { name: String::new(), line: None, entries: Vec::new() }];
+
+  for (idx, raw) in text.lines().enumerate() {
+    let line = Some(idx + 1);
+    let error = |message: &str| Error::Config { line, message: message.to_string() };
+    let trimmed = strip_comment(raw).trim();
+    if trimmed.is_empty() {
+      continue;
+    }
+
+    if let Some(header) = trimmed.strip_prefix('[') {
+      let name = header.strip_suffix(']').ok_or_else(|| error("missing `]`"))?.trim();
+      if name.is_empty() {
+        return Err(error("empty section name"));
+      }
+      if sections.iter().any(|section| section.name == name) {
+        return Err(error(&format!("duplicate section `[{name}]`")));
+      }
+      sections.push(Section { name: name.to_string(), line, entries: Vec::new() });
+      continue;
+    }
+
+    let (key, value) =
+      trimmed.split_once('=').ok_or_else(|| error("expected `key = value` or `[section]`"))?;
+    let key = key.trim();
+    if key.is_empty() {
+      return Err(error("empty key"));
+    }
+
+    let value = value.trim();
+    let value = value.strip_prefix('"').and_then(|value| value.strip_suffix('"')).unwrap_or(value);
+
+    let Some(section) = sections.last_mut() else {
+      unreachable!();
+    };
+    if section.entries.iter().any(|entry| entry.key == key) {
+      return Err(error(&format!("duplicate key `{key}`")));
+    }
+    section.entries.push(Entry { key: key.to_string(), value: value.to_string(), line });
+  }
+
+  Ok(sections)
+}
+
+/// Converts a TOML document into sections, `[appender.name]` tables become `appender.name` sections.
+#[cfg(feature = "toml")]
+fn parse_toml(text: &str) -> Result<Vec<Section>, Error> {
+  use toml::de::{DeTable, DeValue};
+
+  /// Returns the line of the byte offset.
+  fn line_at(text: &str, offset: usize) -> usize {
+    text.get(..offset).unwrap_or(text).matches('\n').count() + 1
+  }
+
+  /// Returns the line of `key` in the spanned table, and the spanned table if its value is one.
+  fn find<'a, 'i>(
+    text: &str,
+    spans: Option<&'a DeTable<'i>>,
+    key: &str,
+  ) -> (Option<usize>, Option<&'a DeTable<'i>>) {
+    let found = spans.and_then(|spans| spans.iter().find(|(name, _)| name.get_ref() == key));
+    let line = found.map(|(name, _)| line_at(text, name.span().start));
+    let table = found.and_then(|(_, value)| match value.get_ref() {
+      DeValue::Table(table) => Some(table),
+      _ => None,
+    });
+    (line, table)
+  }
+
+  /// Converts a scalar value.
+  fn entry(key: &str, value: &toml::Value, line: Option<usize>) -> Result<Entry, Error> {
+    let value = match value {
+      toml::Value::String(value) => value.clone(),
+      toml::Value::Integer(value) => value.to_string(),
+      toml::Value::Float(value) => value.to_string(),
+      toml::Value::Boolean(value) => value.to_string(),
+      _ => {
+        return Err(Error::Config {
+          line,
+          message: format!("`{key}` must be a string, number or bool"),
+        });
+      }
+    };
+    Ok(Entry { key: key.to_string(), value, line })
+  }
+
+  /// Converts all keys of a table.
+  fn section(
+    text: &str,
+    name: String,
+    line: Option<usize>,
+    table: &toml::Table,
+    spans: Option<&DeTable<'_>>,
+  ) -> Result<Section, Error> {
+    let entries = table
+      .iter()
+      .map(|(key, value)| entry(key, value, find(text, spans, key).0))
+      .collect::<Result<_, _>>()?;
+    Ok(Section { name, line, entries })
+  }
+
+  let error = |err: toml::de::Error| Error::Config {
+    line: err.span().map(|span| line_at(text, span.start)),
+    message: err.message().to_string(),
+  };
+  let table = text.parse::<toml::Table>().map_err(error)?;
+  // Parsed again with the positions of the keys, for the lines of errors.
+  let spans = DeTable::parse(text).map_err(error)?;
+  let spans = Some(spans.get_ref());
+
+  let mut top = Section { name: String::new(), line: None, entries: Vec::new() };
+  let mut sections = Vec::new();
+  for (key, value) in &table {
+    let (line, table_spans) = find(text, spans, key);
+    match (key.as_str(), value) {
+      ("appender", toml::Value::Table(appenders)) => {
+        for (name, appender) in appenders {
+          let (line, appender_spans) = find(text, table_spans, name);
+          let toml::Value::Table(appender) = appender else {
+            return Err(Error::Config {
+              line,
+              message: format!("`appender.{name}` must be a table"),
+            });
+          };
+          sections.push(section(text, format!("appender.{name}"), line, appender, appender_spans)?);
+        }
+      }
+      (_, toml::Value::Table(table)) => {
+        sections.push(section(text, key.clone(), line, table, table_spans)?);
+      }
+      _ => top.entries.push(entry(key, value, line)?),
+    }
+  }
+
+  sections.insert(0, top);
+  Ok(sections)
+}
+
+/// Hands out the entries of a section and reports the ones that were never asked for.
+struct Entries {
+  /// Name of the section for error messages
+  section: String,
+  /// The entries that were not taken yet
+  entries: Vec<Entry>,
+}
+
+impl Entries {
+  /// Removes and returns the entry with the key.
+  fn take(&mut self, key: &str) -> Option<Entry> {
+    let idx = self.entries.iter().position(|entry| entry.key == key)?;
+    Some(self.entries.remove(idx))
+  }
+
+  /// Takes the entry and parses its value.
+  fn parse<T>(
+    &mut self,
+    key: &str,
+    parse: impl FnOnce(&str) -> Option<T>,
+    expected: &str,
+  ) -> Result<Option<T>, Error> {
+    let Some(entry) = self.take(key) else {
+      return Ok(None);
+    };
+    parse(&entry.value).map(Some).ok_or_else(|| Error::Config {
+      line: entry.line,
+      message: format!("invalid `{key}` value `{}`, expected {expected}", entry.value),
+    })
+  }
+
+  /// Fails if an entry was not taken.
+  fn finish(self) -> Result<(), Error> {
+    match self.entries.first() {
+      Some(entry) => Err(Error::Config {
+        line: entry.line,
+        message: format!("unknown or unsupported key `{}` in `[{}]`", entry.key, self.section),
+      }),
+      None => Ok(()),
+    }
+  }
+}
+
+/// Parses a level name.
+fn level(value: &str) -> Option<LevelFilter> {
+  LevelFilter::from_str(value).ok()
+}
+
+/// Parses a bool.
+fn boolean(value: &str) -> Option<bool> {
+  match value.to_ascii_lowercase().as_str() {
+    "true" | "yes" | "on" | "1" => Some(true),
+    "false" | "no" | "off" | "0" => Some(false),
+    _ => None,
+  }
+}
+
+/// Parses a size in bytes with an optional `K`, `M` or `G` suffix (powers of 1024).
+fn size(value: &str) -> Option<u64> {
+  let value = value.trim_end_matches(['B', 'b']).trim_end_matches(['i', 'I']);
+  let (digits, factor) = match value.chars().last()?.to_ascii_uppercase() {
+    'K' => (value.get(..value.len() - 1)?, 1024),
+    'M' => (value.get(..value.len() - 1)?, 1024 * 1024),
+    'G' => (value.get(..value.len() - 1)?, 1024 * 1024 * 1024),
+    _ => (value, 1),
+  };
+  digits.trim().parse::<u64>().ok()?.checked_mul(factor)
+}
+
+/// Parses a duration with an `s`, `m`, `h` or `d` suffix.
+fn duration(value: &str) -> Option<Duration> {
+  let (digits, factor) = match value.chars().last()? {
+    's' => (value.get(..value.len() - 1)?, 1),
+    'm' => (value.get(..value.len() - 1)?, 60),
+    'h' => (value.get(..value.len() - 1)?, 3600),
+    'd' => (value.get(..value.len() - 1)?, 24 * 3600),
+    _ => return None,
+  };
+  Some(Duration::from_secs(digits.trim().parse::<u64>().ok()?.checked_mul(factor)?))
+}
+
+/// Parses `utc`, `local` or an offset like `+01:30`.
+fn time_zone(value: &str) -> Option<TimeZone> {
+  match value.to_ascii_lowercase().as_str() {
+    "utc" | "z" => Some(TimeZone::Utc),
+    "local" => Some(TimeZone::Local),
+    offset => {
+      let (sign, offset) = match offset.split_at_checked(1)? {
+        ("+", offset) => (1, offset),
+        ("-", offset) => (-1, offset),
+        _ => return None,
+      };
+      let (hours, minutes) = offset.split_once(':').unwrap_or((offset, "0"));
+      // `parse` would accept another sign.
+      let number = |value: &str| {
+        value.bytes().all(|byte| byte.is_ascii_digit()).then(|| value.parse::<u8>().ok())?
+      };
+      let (hours, minutes) = (i32::from(number(hours)?), i32::from(number(minutes)?));
+      let seconds = hours * 3600 + minutes * 60;
+      (minutes < 60 && seconds < 24 * 3600).then_some(TimeZone::FixedOffset(sign * seconds))
+    }
+  }
+}
+
+/// Parses a timestamp format, values containing `%` are strftime formats.
+fn timestamp(value: &str) -> Option<TimestampFormat> {
+  match value {
+    "default" => Some(TimestampFormat::Default),
+    "rfc3339" => Some(TimestampFormat::Rfc3339),
+    "iso8601" => Some(TimestampFormat::Iso8601),
+    "epoch_millis" => Some(TimestampFormat::EpochMillis),
+    "uptime" => Some(TimestampFormat::Uptime),
+    strftime if strftime.contains('%') => Some(TimestampFormat::Strftime(strftime.to_string())),
+    _ => None,
+  }
+}
+
+/// Parses a thread format.
+fn thread(value: &str) -> Option<ThreadFormat> {
+  match value {
+    "id" => Some(ThreadFormat::Id),
+    "name" => Some(ThreadFormat::Name),
+    "os_id" => Some(ThreadFormat::OsId),
+    "name_and_os_id" => Some(ThreadFormat::NameAndOsId),
+    _ => None,
+  }
+}
+
+/// Parses a color choice, bools are accepted as `always`/`never`.
+fn color(value: &str) -> Option<ColorChoice> {
+  match value {
+    "auto" => Some(ColorChoice::Auto),
+    "always" => Some(ColorChoice::Always),
+    "never" => Some(ColorChoice::Never),
+    value => {
+      boolean(value).map(|color| if color { ColorChoice::Always } else { ColorChoice::Never })
+    }
+  }
+}
+
+/// Parses a syslog facility name or code.
+#[cfg_attr(not(unix), expect(dead_code, reason = "syslog is only available on unix"))]
+fn facility(value: &str) -> Option<u8> {
+  let code = match value {
+    "kern" => 0,
+    "user" => 1,
+    "mail" => 2,
+    "daemon" => 3,
+    "auth" => 4,
+    "syslog" => 5,
+    local => match local.strip_prefix("local") {
+      Some(n) => 16 + n.parse::<u8>().ok().filter(|n| *n < 8)?,
+      None => local.parse::<u8>().ok().filter(|code| *code < 24)?,
+    },
+  };
+  Some(code)
+}
+
+/// Where an appender writes to.
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Kind {
+  /// `StdoutAppender`
+  Stdout {
+    /// Use `StdoutAppender::buffered`
+    buffered: bool,
+  },
+  /// `StderrAppender`
+  Stderr {
+    /// Use `StderrAppender::buffered`
+    buffered: bool,
+  },
+  /// A file opened for appending
+  File(PathBuf),
+  /// `RollingFileAppender`
+  Rolling {
+    /// Path of the current file
+    path: PathBuf,
+    /// Size after which the file is rotated
+    max_size: u64,
+    /// Number of archives
+    keep: usize,
+    /// Total size of the archives
+    max_total_size: Option<u64>,
+    /// Age of the archives
+    max_age: Option<Duration>,
+    /// Gzip the archives
+    compress: bool,
+  },
+  /// `DatedFileAppender` with the template of the path
+  Dated(String),
+  /// A TCP connection
+  Tcp(String),
+  /// `SyslogAppender`
+  Syslog {
+    /// Path of the socket
+    path: PathBuf,
+    /// Name of the program
+    ident: Option<String>,
+    /// Facility code
+    facility: u8,
+  },
+}
+
+/// How an appender formats records.
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Format {
+  /// The default format, the color is resolved when the appender is built
+  Default(DefaultFormatOptions, ColorChoice),
+  /// One JSON object per line
+  Json,
+  /// One logfmt line per record
+  Logfmt,
+  /// A custom pattern
+  Pattern(Pattern),
+}
+
+impl Format {
+  /// Formats the record.
+  fn format_into(
+    &self,
+    ctx: &LogContext<'_>,
+    record: &Record<'_>,
+    buf: &mut String,
+  ) -> std::fmt::Result {
+    match self {
+      Self::Default(options, _) => options.format_with_context(ctx, record, buf),
+      Self::Json => formats::json(ctx, record, buf),
+      Self::Logfmt => formats::logfmt(ctx, record, buf),
+      Self::Pattern(pattern) => pattern.format_into(ctx, record, buf),
+    }
+  }
+}
+
+/// A configured appender.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct AppenderConfig {
+  /// The line of the section header
+  line: Option<usize>,
+  /// Where the appender writes to
+  kind: Kind,
+  /// Level of targets without a directive
+  level: LevelFilter,
+  /// How records are formatted
+  format: Format,
+}
+
+impl AppenderConfig {
+  /// Reads the appender from its section.
+  fn parse(
+    name: &str,
+    line: Option<usize>,
+    mut entries: Entries,
+    default_level: LevelFilter,
+  ) -> Result<Self, Error> {
+    let kind_entry = entries
+      .take("kind")
+      .ok_or_else(|| Error::Config { line, message: format!("appender `{name}` has no `kind`") })?;
+    let path = |entries: &mut Entries| {
+      entries
+        .take("path")
+        .map(|entry| PathBuf::from(entry.value))
+        .ok_or_else(|| Error::Config { line, message: format!("appender `{name}` has no `path`") })
+    };
+
+    let kind = match kind_entry.value.as_str() {
+      "stdout" => {
+        Kind::Stdout { buffered: entries.parse("buffered", boolean, "a bool")?.unwrap_or(false) }
+      }
+      "stderr" => {
+        Kind::Stderr { buffered: entries.parse("buffered", boolean, "a bool")?.unwrap_or(false) }
+      }
+      "file" => Kind::File(path(&mut entries)?),
+      "dated" => Kind::Dated(
+        entries
+          .take("path")
+          .ok_or_else(|| Error::Config {
+            line,
+            message: format!("appender `{name}` has no `path`"),
+          })?
+          .value,
+      ),
+      "rolling" => Kind::Rolling {
+        path: path(&mut entries)?,
+        max_size: entries.parse("max_size", size, "a size like 10M")?.unwrap_or(10 * 1024 * 1024),
+        keep: entries.parse("keep", |keep| keep.parse().ok(), "a number")?.unwrap_or(5),
+        max_total_size: entries.parse("max_total_size", size, "a size like 100M")?,
+        max_age: entries.parse("max_age", duration, "a duration like 7d")?,
+        compress: match entries.parse("compress", boolean, "a bool")? {
+          Some(true) if !cfg!(feature = "compression") => {
+            return Err(Error::Config {
+              line,
+              message: format!("appender `{name}` requires the `compression` feature to compress"),
+            });
+          }
+          compress => compress.unwrap_or(false),
+        },
+      },
+      "tcp" => Kind::Tcp(
+        entries
+          .take("address")
+          .ok_or_else(|| Error::Config {
+            line,
+            message: format!("appender `{name}` has no `address`"),
+          })?
+          .value,
+      ),
+      #[cfg(unix)]
+      "syslog" => Kind::Syslog {
+        path: entries
+          .take("path")
+          .map_or_else(|| PathBuf::from("/dev/log"), |entry| PathBuf::from(entry.value)),
+        ident: entries.take("ident").map(|entry| entry.value),
+        facility: entries
+          .parse("facility", facility, "a facility like user or local0")?
+          .unwrap_or(1),
+      },
+      #[cfg(not(unix))]
+      "syslog" => {
+        return Err(Error::Config {
+          line: kind_entry.line,
+          message: "the syslog appender is only available on unix".to_string(),
+        });
+      }
+      other => {
+        return Err(Error::Config {
+          line: kind_entry.line,
+          message: format!("unknown appender kind `{other}`, expected stdout, stderr, file, rolling, dated, tcp or syslog"),
+        });
+      }
+    };
+
+    let level = entries.parse("level", level, "a level like info")?.unwrap_or(default_level);
+    let format_entry = entries.take("format");
+    let format_line = format_entry.as_ref().and_then(|entry| entry.line);
+    let format = match format_entry.as_ref().map_or("default", |entry| entry.value.as_str()) {
+      "default" => Self::parse_default_format(&mut entries)?,
+      "json" => Format::Json,
+      "logfmt" => Format::Logfmt,
+      "pattern" => {
+        let pattern = entries.take("pattern").ok_or_else(|| Error::Config {
+          line: format_line,
+          message: format!("appender `{name}` uses the pattern format but has no `pattern`"),
+        })?;
+        Format::Pattern(Pattern::parse(&pattern.value, pattern.line)?)
+      }
+      other => {
+        return Err(Error::Config {
+          line: format_line,
+          message: format!("unknown format `{other}`, expected default, json, logfmt or pattern"),
+        });
+      }
+    };
+
+    entries.finish()?;
+    Ok(Self { line, kind, level, format })
+  }
+
+  /// Reads the options of the default format.
+  fn parse_default_format(entries: &mut Entries) -> Result<Format, Error> {
+    let mut options = DefaultFormatOptions::new();
+    if let Some(time_zone) =
+      entries.parse("time_zone", time_zone, "utc, local or an offset like +01:00")?
+    {
+      options = options.time_zone(time_zone);
+    }
+    if let Some(timestamp) = entries.parse(
+      "timestamp",
+      timestamp,
+      "default, rfc3339, iso8601, epoch_millis, uptime or a strftime format",
+    )? {
+      options = options.timestamp(timestamp);
+    }
+    if let Some(thread) = entries.parse("thread", thread, "id, name, os_id or name_and_os_id")? {
+      options = options.thread(thread);
+    }
+    if let Some(target) = entries.parse("target", boolean, "a bool")? {
+      options = options.target(target);
+    }
+    if let Some(location) = entries.parse("location", boolean, "a bool")? {
+      options = options.location(location);
+    }
+    if let Some(sequence) = entries.parse("sequence", boolean, "a bool")? {
+      options = options.sequence(sequence);
+    }
+    if let Some(delta) = entries.parse("delta", boolean, "a bool")? {
+      options = options.delta(delta);
+    }
+    let color =
+      entries.parse("color", color, "auto, always or never")?.unwrap_or(ColorChoice::Never);
+    Ok(Format::Default(options, color))
+  }
+
+  /// Opens the file or connection of the appender.
+  fn open(&self) -> Result<Arc<dyn Appender<String>>, Error> {
+    let error = |what: &str, err: io::Error| Error::Config {
+      line: self.line,
+      message: format!("cannot {what}: {err}"),
+    };
+    let appender = match &self.kind {
+      Kind::Stdout { buffered: false } => StdoutAppender::new().into_appender(),
+      Kind::Stdout { buffered: true } => StdoutAppender::buffered().into_appender(),
+      Kind::Stderr { buffered: false } => StderrAppender::new().into_appender(),
+      Kind::Stderr { buffered: true } => StderrAppender::buffered().into_appender(),
+      Kind::File(path) => OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .map_err(|err| error(&format!("open {}", path.display()), err))?
+        .into_appender(),
+      Kind::Dated(template) => DatedFileAppender::new(template.as_str())
+        .map_err(|err| error(&format!("open {template}"), err))?
+        .into_appender(),
+      Kind::Rolling { path, max_size, keep, max_total_size, max_age, compress } => {
+        let mut rolling = RollingFileAppender::new(path, *max_size)
+          .map_err(|err| error(&format!("open {}", path.display()), err))?
+          .keep(*keep);
+        if let Some(max_total_size) = max_total_size {
+          rolling = rolling.max_total_size(*max_total_size);
+        }
+        if let Some(max_age) = max_age {
+          rolling = rolling.max_age(*max_age);
+        }
+        #[cfg(feature = "compression")]
+        if *compress {
+          rolling = rolling.compress();
+        }
+        // Rejected when parsing without the feature.
+        #[cfg(not(feature = "compression"))]
+        let _ = compress;
+        rolling.into_appender()
+      }
+      Kind::Tcp(address) => BufWriter::new(
+        TcpStream::connect(address).map_err(|err| error(&format!("connect to {address}"), err))?,
+      )
+      .into_appender(),
+      #[cfg(unix)]
+      Kind::Syslog { path, ident, facility } => {
+        let syslog = crate::SyslogAppender::with_path(path)
+          .map_err(|err| error(&format!("connect to {}", path.display()), err))?
+          .facility(*facility);
+        match ident {
+          Some(ident) => syslog.ident(ident.clone()).into_appender(),
+          None => syslog.into_appender(),
+        }
+      }
+      #[cfg(not(unix))]
+      Kind::Syslog { .. } => unreachable!(),
+    };
+    Ok(appender)
+  }
+
+  /// The format with the color resolved for the stream the appender writes to.
+  fn resolved_format(&self) -> Format {
+    match (&self.format, &self.kind) {
+      (Format::Default(options, color), kind) => {
+        let enabled = match kind {
+          Kind::Stdout { .. } => color.enabled(&io::stdout()),
+          Kind::Stderr { .. } => color.enabled(&io::stderr()),
+          _ => *color == ColorChoice::Always,
+        };
+        Format::Default(options.clone().color(enabled), ColorChoice::Never)
+      }
+      (format, _) => format.clone(),
+    }
+  }
+}
+
+/// Applies the target directives before forwarding to the configured appender.
+struct Filtered {
+  /// The appender
+  inner: Arc<dyn Appender<String>>,
+  /// Level of targets without a directive
+  level: LevelFilter,
+  /// Target prefixes and their levels, longest prefix first
+  targets: Arc<[(String, LevelFilter)]>,
+}
+
+impl Filtered {
+  /// Returns the level for the target, from the longest matching directive or the level of the appender.
+  fn level_for(&self, target: &str) -> LevelFilter {
+    self
+      .targets
+      .iter()
+      .find(|(prefix, _)| {
+        target
+          .strip_prefix(prefix.as_str())
+          .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+      })
+      .map_or(self.level, |(_, level)| *level)
+  }
+}
+
+impl Appender<String> for Filtered {
+  fn append_log_message(&self, message: &String) {
+    self.inner.append_log_message(message);
+  }
+
+  fn append_log_record(&self, record: &Record<'_>, message: &String) {
+    if record.level() <= self.level_for(record.target()) {
+      self.inner.append_log_record(record, message);
+    }
+  }
+
+  fn append_log_context(&self, ctx: &LogContext<'_>, record: &Record<'_>, message: &String) {
+    if record.level() <= self.level_for(record.target()) {
+      self.inner.append_log_context(ctx, record, message);
+    }
+  }
+
+  fn flush(&self) {
+    self.inner.flush();
+  }
+
+  fn dump(&self, out: &mut dyn io::Write) {
+    self.inner.dump(out);
+  }
+
+  fn dropped(&self) -> u64 {
+    self.inner.dropped()
+  }
+}
+
+/// A parsed configuration, see the module documentation for the format.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Config {
+  /// Target prefixes and their levels, longest prefix first
+  targets: Vec<(String, LevelFilter)>,
+  /// The appenders in the order they were configured
+  appenders: Vec<AppenderConfig>,
+}
+
+impl Config {
+  /// Reads a configuration from sections.
+  fn from_sections(sections: Vec<Section>) -> Result<Self, Error> {
+    let mut default_level = LevelFilter::Info;
+    let mut targets = Vec::new();
+    let mut appenders = Vec::new();
+
+    for section in sections {
+      let mut entries = Entries { section: section.name.clone(), entries: section.entries };
+      if section.name.is_empty() {
+        default_level =
+          entries.parse("level", level, "a level like info")?.unwrap_or(default_level);
+        entries.finish()?;
+      } else if section.name == "targets" {
+        for entry in entries.entries {
+          let level = level(&entry.value).ok_or_else(|| Error::Config {
+            line: entry.line,
+            message: format!("invalid level `{}` for target `{}`", entry.value, entry.key),
+          })?;
+          targets.push((entry.key, level));
+        }
+      } else if let Some(name) = section.name.strip_prefix("appender.") {
+        appenders.push((name.to_string(), section.line, entries));
+      } else {
+        return Err(Error::Config {
+          line: section.line,
+          message: format!(
+            "unknown section `[{}]`, expected `[targets]` or `[appender.<name>]`",
+            section.name
+          ),
+        });
+      }
+    }
+
+    // Appenders are parsed last, the global level may come after them in TOML.
+    let appenders = appenders
+      .into_iter()
+      .map(|(name, line, entries)| AppenderConfig::parse(&name, line, entries, default_level))
+      .collect::<Result<_, _>>()?;
+
+    targets.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+    Ok(Self { targets, appenders })
+  }
+
+  /// Opens the files and connections of the appenders and registers them on a new `Builder`.
+  /// Appenders with the same format share it.
+  /// # Errors
+  /// If a file cannot be opened or a connection cannot be established.
+  pub fn builder(&self) -> Result<Builder, Error> {
+    let targets: Arc<[(String, LevelFilter)]> = self.targets.clone().into();
+    let max_target = self.targets.iter().map(|(_, level)| *level).max().unwrap_or(LevelFilter::Off);
+
+    let mut groups: Vec<(Format, Vec<(LevelFilter, Filtered)>)> = Vec::new();
+    for appender in &self.appenders {
+      let filtered =
+        Filtered { inner: appender.open()?, level: appender.level, targets: Arc::clone(&targets) };
+      // Records must reach the filter for targets that are more verbose than the appender.
+      let filter = std::cmp::max(appender.level, max_target);
+      let format = appender.resolved_format();
+      match groups.iter_mut().find(|(existing, _)| *existing == format) {
+        Some((_, group)) => group.push((filter, filtered)),
+        None => groups.push((format, vec![(filter, filtered)])),
+      }
+    }
+
+    let mut builder = Builder::default();
+    for (format, appenders) in groups {
+      let format = move |ctx: &LogContext<'_>, record: &Record<'_>, buf: &mut String| {
+        format.format_into(ctx, record, buf)
+      };
+      builder = builder.handler(
+        Box::new(buffer::FormatInto(format)),
+        |mut group: AppenderBuilder<String>| {
+          for (filter, appender) in appenders {
+            group = group.appender_filter(filter, appender);
+          }
+          group
+        },
+      );
+    }
+
+    Ok(builder)
+  }
+
+  /// Builds and initializes the logger, replacing the current configuration.
+  /// # Errors
+  /// If `builder` fails or another logger implementation is in use.
+  pub fn init(&self) -> Result<(), Error> {
+    self.builder()?.init()
+  }
+}
+
+/// Parses a configuration in the INI-like format.
+/// # Errors
+/// If the configuration is invalid, the error contains the line number.
+pub fn parse(text: &str) -> Result<Config, Error> {
+  Config::from_sections(parse_ini(text)?)
+}
+
+/// Parses a TOML configuration with the same sections and keys as the INI-like format.
+/// # Errors
+/// If the configuration is invalid. Syntax errors contain the line number.
+#[cfg(feature = "toml")]
+pub fn from_toml(text: &str) -> Result<Config, Error> {
+  Config::from_sections(parse_toml(text)?)
+}
+
+/// Reads and parses the configuration file at `path`.
+/// Files ending in `.toml` are parsed as TOML if the `toml` feature is enabled, everything else with `parse`.
+/// # Errors
+/// If the file cannot be read or the configuration is invalid.
+pub fn load(path: impl AsRef<Path>) -> Result<Config, Error> {
+  let path = path.as_ref();
+  let text = std::fs::read_to_string(path).map_err(|err| Error::Config {
+    line: None,
+    message: format!("cannot read {}: {err}", path.display()),
+  })?;
+  parse_file(path, &text)
+}
+
+/// Parses the contents of the file at `path`, as TOML or with `parse` depending on its extension.
+pub(crate) fn parse_file(path: &Path, text: &str) -> Result<Config, Error> {
+  #[cfg(feature = "toml")]
+  if path.extension().is_some_and(|extension| extension == "toml") {
+    return from_toml(text);
+  }
+  #[cfg(not(feature = "toml"))]
+  {
+    _ = path;
+  }
+
+  parse(text)
+}
+
+#[cfg(test)]
+mod test {
+  use std::sync::{Arc, Mutex};
+  use std::time::{Duration, Instant, UNIX_EPOCH};
+
+  use log::{Level, LevelFilter, Record};
+
+  use crate::config::{self, Filtered, Format, Kind};
+  use crate::{
+    Appender, DefaultFormatOptions, Error, IntoAppender, LogContext, ThreadFormat, TimeZone,
+  };
+
+  #[test]
+  fn parse() {
+    let config = config::parse(
+      "
+# comment
+level = warn
+
+[targets]
+hyper = error
+app::db = trace
+
+[appender.console]
+kind = stderr
+thread = name
+
+[appender.file]
+kind = rolling
+path = /tmp/app.log
+max_size = 1M
+max_age = 7d
+level = debug
+format = pattern
+pattern = \"{level} {message} \"
+",
+    )
+    .expect("parse");
+
+    assert_eq!(
+      config.targets,
+      [("app::db".to_string(), LevelFilter::Trace), ("hyper".to_string(), LevelFilter::Error)]
+    );
+    let [console, file] = config.appenders.as_slice() else {
+      panic!("expected two appenders");
+    };
+    assert_eq!(console.kind, Kind::Stderr { buffered: false });
+    assert_eq!(console.level, LevelFilter::Warn);
+    assert!(
+      matches!(&console.format, Format::Default(options, _) if *options == DefaultFormatOptions::new().thread(ThreadFormat::Name))
+    );
+    assert_eq!(file.line, Some(13));
+    assert_eq!(
+      file.kind,
+      Kind::Rolling {
+        path: "/tmp/app.log".into(),
+        max_size: 1024 * 1024,
+        keep: 5,
+        max_total_size: None,
+        max_age: Some(Duration::from_secs(7 * 24 * 3600)),
+        compress: false,
+      }
+    );
+    assert_eq!(file.level, LevelFilter::Debug);
+    assert!(matches!(file.format, Format::Pattern(_)));
+  }
+
+  /// The example of the module documentation.
+  #[test]
+  fn documented_example() {
+    let config = config::parse(
+      r#"
+# Level of appenders that don't set one, defaults to info
+level = info
+
+# Overrides the level for targets (module paths) and their children
+[targets]
+hyper = warn
+my_app::db = trace
+
+[appender.console]
+kind = stderr            # stdout, stderr, file, rolling, dated, tcp or syslog
+format = default         # default, json, logfmt or pattern
+color = auto
+thread = name
+
+[appender.file]
+kind = rolling
+path = /var/log/my_app.log
+max_size = 10M
+keep = 5
+level = debug
+format = pattern
+pattern = "{timestamp} {level} [{thread}] {target} - {message}"
+"#,
+    )
+    .expect("parse");
+
+    let [console, file] = config.appenders.as_slice() else {
+      panic!("expected two appenders");
+    };
+    assert_eq!(console.kind, Kind::Stderr { buffered: false });
+    assert!(matches!(console.format, Format::Default(..)));
+    assert_eq!(file.level, LevelFilter::Debug);
+    assert!(matches!(file.format, Format::Pattern(_)));
+    assert!(config::parse("[appender.a]\nkind = stdout;comment").is_err());
+    assert!(config::parse("[appender.a]\nkind = stdout ; comment\nformat = \"json\" # x").is_ok());
+  }
+
+  #[test]
+  fn errors() {
+    let error = |text: &str| match config::parse(text) {
+      Err(Error::Config { line, message }) => (line, message),
+      other => panic!("expected an error, got {other:?}"),
+    };
+
+    assert_eq!(error("level = loud").0, Some(1));
+    assert_eq!(error("\n[appender.a]\nkind = carrier_pigeon").0, Some(3));
+    assert_eq!(error("[appender.a]\nkind = stdout\nformat = xml").0, Some(3));
+    assert_eq!(error("[appender.a]\nkind = stdout\nformat = pattern\npattern = {nope}").0, Some(4));
+    assert_eq!(error("[appender.a]\nkind = stdout\nformat = json\nthread = name").0, Some(4));
+    assert_eq!(error("[appender.a]\nkind = file").0, Some(1));
+    assert_eq!(error("[appender.a]\nkind = dated").0, Some(1));
+    assert_eq!(error("[appender.a]\nkind = rolling\npath = a.log\nmax_age = forever").0, Some(4));
+    assert_eq!(error("\n\n[oops").0, Some(3));
+    assert_eq!(error("[loggers]").0, Some(1));
+    assert_eq!(error("no equals sign").0, Some(1));
+    assert!(error("[appender.a]\nkind = carrier_pigeon").1.contains("carrier_pigeon"));
+  }
+
+  #[test]
+  fn time_zones() {
+    assert_eq!(config::time_zone("UTC"), Some(TimeZone::Utc));
+    assert_eq!(config::time_zone("+01:30"), Some(TimeZone::FixedOffset(5400)));
+    assert_eq!(config::time_zone("-05"), Some(TimeZone::FixedOffset(-18000)));
+    for invalid in ["+-5", "-+5", "+01:-30", "+01:+30", "+01:60", "+24:00", "+", "+01:", "01:00"] {
+      assert_eq!(config::time_zone(invalid), None, "{invalid}");
+    }
+  }
+
+  #[test]
+  fn target_directives() {
+    let passed = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&passed);
+    let filtered = Filtered {
+      inner: (move |msg: &String| sink.lock().expect("lock").push(msg.clone())).into_appender(),
+      level: LevelFilter::Info,
+      targets: vec![
+        ("app::db".to_string(), LevelFilter::Trace),
+        ("app".to_string(), LevelFilter::Warn),
+      ]
+      .into(),
+    };
+
+    assert_eq!(filtered.level_for("app::db::pool"), LevelFilter::Trace);
+    assert_eq!(filtered.level_for("app::dbx"), LevelFilter::Warn);
+    assert_eq!(filtered.level_for("app"), LevelFilter::Warn);
+    assert_eq!(filtered.level_for("application"), LevelFilter::Info);
+
+    let extensions = Vec::new();
+    let ctx = LogContext::new(UNIX_EPOCH, 0, Instant::now(), None, &extensions);
+    for (level, target) in [
+      (Level::Info, "app"),
+      (Level::Warn, "app"),
+      (Level::Trace, "app::db::pool"),
+      (Level::Debug, "app::dbx"),
+      (Level::Info, "application"),
+      (Level::Debug, "application"),
+    ] {
+      let record = Record::builder().level(level).target(target).build();
+      let msg = format!("{level} {target}");
+      filtered.append_log_record(&record, &msg);
+      filtered.append_log_context(&ctx, &record, &msg);
+    }
+
+    assert_eq!(
+      *passed.lock().expect("lock"),
+      [
+        "WARN app",
+        "WARN app",
+        "TRACE app::db::pool",
+        "TRACE app::db::pool",
+        "INFO application",
+        "INFO application"
+      ]
+    );
+  }
+
+  #[cfg(feature = "toml")]
+  #[test]
+  fn toml() {
+    let config = config::from_toml(
+      "
+level = \"debug\"
+
+[targets]
+hyper = \"warn\"
+
+[appender.console]
+kind = \"stdout\"
+format = \"logfmt\"
+buffered = true
+",
+    )
+    .expect("parse");
+    let [console] = config.appenders.as_slice() else {
+      panic!("expected one appender");
+    };
+    assert_eq!(console.kind, Kind::Stdout { buffered: true });
+    assert_eq!(console.level, LevelFilter::Debug);
+    assert_eq!(console.format, Format::Logfmt);
+
+    assert!(matches!(config::from_toml("level = \n"), Err(Error::Config { line: Some(1), .. })));
+    assert!(matches!(
+      config::from_toml("a = 1\n!bad = 2\n"),
+      Err(Error::Config { line: Some(2), .. })
+    ));
+
+    // Errors found after parsing point at the key.
+    for (text, line) in [
+      ("level = \"debug\"\nformat = [1]\n", 2),
+      ("level = \"loud\"\n", 1),
+      ("\n[appender]\nconsole = 1\n", 3),
+      ("\n[appender.console]\nkind = \"stdout\"\n\nlevel = \"loud\"\n", 5),
+      ("\n[appender.console]\nkind = \"stdout\"\ncolour = true\n", 4),
+    ] {
+      let err = config::from_toml(text).err();
+      assert!(
+        matches!(err, Some(Error::Config { line: Some(l), .. }) if l == line),
+        "{text}: {err:?}"
+      );
+    }
+  }
+}
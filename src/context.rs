@@ -0,0 +1,150 @@
+//!
+//! The default format prints the context after the thread (and target/location), `key=value` separated by spaces.
+//! Custom format fns read it with `for_each`.
+//!
+//! Fields set with `Builder::global_field` are attached to the records of all threads,
+//! format fns read them with `LogContext::for_each_global_field`.
+//!
+//!```rust
+//! use trivial_log::context;
+//!
+//! context::scope(&[("request_id", "abc")], || {
+//!   // [I] - ... - ThreadId(1) - request_id=abc - handling request
+//!   log::info!("handling request");
+//! });
+//!
+//! let _guard = context::push(&[("user_id", "7")]);
+//! log::info!("logged with user_id=7");
+//!```
+use std::cell::RefCell;
+use std::marker::PhantomData;
+
+thread_local! {
+  /// The context of the current thread in the order it was pushed.
+  static CONTEXT: RefCell<Vec<(String, String)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The fields set with `Builder::global_field`.
+pub(crate) struct GlobalFields(pub(crate) Vec<(String, String)>);
+
+/// Removes the key-value pairs added by `push` when dropped.
+///
+/// Guards should be dropped in the reverse order they were created in,
+/// dropping a guard also removes everything that was pushed after it.
+#[must_use = "the context is removed when the guard is dropped"]
+pub struct ContextGuard {
+  /// Length of the context before the push
+  len: usize,
+  /// The context is thread local, so the guard must not be sent to another thread.
+  _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for ContextGuard {
+  fn drop(&mut self) {
+    _ = CONTEXT.try_with(|context| {
+      if let Ok(mut context) = context.try_borrow_mut() {
+        context.truncate(self.len);
+      }
+    });
+  }
+}
+
+/// Adds the key-value pairs to the context of the current thread until the returned guard is dropped.
+/// A key that is already in the context is shadowed until then.
+pub fn push(entries: &[(&str, &str)]) -> ContextGuard {
+  let len = CONTEXT
+    .try_with(|context| {
+      let Ok(mut context) = context.try_borrow_mut() else {
+        return usize::MAX;
+      };
+
+      let len = context.len();
+      context.extend(entries.iter().map(|(key, value)| ((*key).to_string(), (*value).to_string())));
+      len
+    })
+    .unwrap_or(usize::MAX);
+
+  ContextGuard { len, _not_send: PhantomData }
+}
+
+/// Calls `func` with the key-value pairs added to the context of the current thread and returns its result.
+pub fn scope<R>(entries: &[(&str, &str)], func: impl FnOnce() -> R) -> R {
+  let _guard = push(entries);
+  func()
+}
+
+/// Calls `func` for every key-value pair in the context of the current thread, in the order they were pushed.
+/// Shadowed keys are skipped.
+pub fn for_each(mut func: impl FnMut(&str, &str)) {
+  _ = CONTEXT.try_with(|context| {
+    let Ok(context) = context.try_borrow() else {
+      return;
+    };
+
+    for (idx, (key, value)) in context.iter().enumerate() {
+      let shadowed = context.iter().skip(idx + 1).any(|(later, _)| later == key);
+      if !shadowed {
+        func(key, value);
+      }
+    }
+  });
+}
+
+/// Returns true if the context of the current thread is empty.
+#[must_use]
+pub fn is_empty() -> bool {
+  CONTEXT
+    .try_with(|context| context.try_borrow().map_or(true, |context| context.is_empty()))
+    .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod test {
+  use std::time::{Instant, UNIX_EPOCH};
+
+  use log::Record;
+
+  use crate::context::{self, GlobalFields};
+  use crate::{DefaultFormatOptions, LogContext};
+
+  fn collect() -> Vec<String> {
+    let mut entries = Vec::new();
+    context::for_each(|key, value| entries.push(format!("{key}={value}")));
+    entries
+  }
+
+  #[test]
+  fn nesting() {
+    assert!(context::is_empty());
+    context::scope(&[("request_id", "abc"), ("user_id", "1")], || {
+      assert_eq!(collect(), ["request_id=abc", "user_id=1"]);
+
+      let guard = context::push(&[("user_id", "2")]);
+      assert_eq!(collect(), ["request_id=abc", "user_id=2"]);
+      drop(guard);
+
+      assert_eq!(collect(), ["request_id=abc", "user_id=1"]);
+      std::thread::spawn(|| assert!(context::is_empty())).join().expect("join");
+    });
+    assert!(context::is_empty());
+  }
+
+  #[test]
+  fn global_fields() {
+    let fields = GlobalFields(vec![("service".to_string(), "billing".to_string())]);
+    let extensions = Vec::new();
+    let ctx = LogContext::new(UNIX_EPOCH, 0, Instant::now(), Some(&fields), &extensions);
+
+    let record = Record::builder().args(format_args!("paid")).build();
+    let mut msg = String::new();
+    context::scope(&[("request_id", "abc")], || {
+      DefaultFormatOptions::new().format_with_context(&ctx, &record, &mut msg)
+    })
+    .expect("format failed");
+    assert!(msg.ends_with(" - service=billing request_id=abc - paid\n"), "{msg}");
+
+    // Without the logger there are no global fields.
+    let msg = DefaultFormatOptions::new().format(UNIX_EPOCH, &record).expect("format failed");
+    assert!(!msg.contains("service"), "{msg}");
+  }
+}
@@ -0,0 +1,363 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::Record;
+
+use crate::{Appender, LogContext};
+
+/// A parsed strftime path template.
+#[cfg(feature = "chrono")]
+struct Template(Vec<chrono::format::Item<'static>>);
+
+#[cfg(feature = "chrono")]
+impl Template {
+  /// Parses the template, `None` if it is invalid.
+  fn parse(template: &str) -> Option<Self> {
+    chrono::format::StrftimeItems::new(template).parse_to_owned().ok().map(Self)
+  }
+
+  /// Expands the template for the unix time `secs` in UTC.
+  fn expand(&self, secs: i64) -> Option<String> {
+    use std::fmt::Write;
+
+    let utc = chrono::DateTime::from_timestamp(secs, 0)?;
+    let mut path = String::new();
+    write!(path, "{}", utc.format_with_items(self.0.iter())).ok()?;
+    Some(path)
+  }
+}
+
+/// A part of a path template.
+#[cfg(not(feature = "chrono"))]
+enum Item {
+  /// Text that is copied into the path
+  Literal(String),
+  /// `%Y`
+  Year,
+  /// `%m`
+  Month,
+  /// `%d`
+  Day,
+  /// `%H`
+  Hour,
+  /// `%M`
+  Minute,
+  /// `%S`
+  Second,
+}
+
+/// A parsed path template with `%Y`, `%m`, `%d`, `%H`, `%M`, `%S` and `%%`.
+#[cfg(not(feature = "chrono"))]
+struct Template(Vec<Item>);
+
+#[cfg(not(feature = "chrono"))]
+impl Template {
+  /// Parses the template, `None` if it contains another specifier.
+  fn parse(template: &str) -> Option<Self> {
+    let mut items = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+      if c != '%' {
+        literal.push(c);
+        continue;
+      }
+
+      let item = match chars.next()? {
+        'Y' => Item::Year,
+        'm' => Item::Month,
+        'd' => Item::Day,
+        'H' => Item::Hour,
+        'M' => Item::Minute,
+        'S' => Item::Second,
+        '%' => {
+          literal.push('%');
+          continue;
+        }
+        _ => return None,
+      };
+      if !literal.is_empty() {
+        items.push(Item::Literal(std::mem::take(&mut literal)));
+      }
+      items.push(item);
+    }
+    if !literal.is_empty() {
+      items.push(Item::Literal(literal));
+    }
+    Some(Self(items))
+  }
+
+  /// Expands the template for the unix time `secs` in UTC.
+  fn expand(&self, secs: i64) -> Option<String> {
+    use std::fmt::Write;
+
+    let dt = crate::calendar::CivilDateTime::from_unix_millis(secs.saturating_mul(1000), 0);
+    let mut path = String::new();
+    for item in &self.0 {
+      let result = match item {
+        Item::Literal(text) => path.write_str(text),
+        Item::Year => write!(path, "{:04}", dt.year),
+        Item::Month => write!(path, "{:02}", dt.month),
+        Item::Day => write!(path, "{:02}", dt.day),
+        Item::Hour => write!(path, "{:02}", dt.hour),
+        Item::Minute => write!(path, "{:02}", dt.minute),
+        Item::Second => write!(path, "{:02}", dt.second),
+      };
+      result.ok()?;
+    }
+    Some(path)
+  }
+}
+
+/// Seconds since the unix epoch, negative before it.
+fn unix_secs(time: SystemTime) -> i64 {
+  match time.duration_since(UNIX_EPOCH) {
+    Ok(since) => i64::try_from(since.as_secs()).unwrap_or(i64::MAX),
+    Err(err) => i64::try_from(err.duration().as_secs()).map_or(i64::MIN, |secs| -secs),
+  }
+}
+
+/// The open file.
+struct Current {
+  /// The file at `path`
+  file: File,
+  /// The expanded template
+  path: String,
+  /// The second the path was last expanded for
+  secs: i64,
+}
+
+/// Appender that writes to a file whose path is a strftime template, such as `logs/app-%Y-%m-%d.log`.
+///
+/// The template is expanded in UTC for the time of each record, the one passed to format fns as `LogContext::now`.
+/// Once a record expands to a different path the previous file is closed and the next one is opened,
+/// so `app-%Y-%m-%d.log` switches to a new file at midnight UTC. Nothing is renamed.
+/// A record that is older than the current file, because another thread was slower, is written to the current file.
+/// Missing directories are created.
+///
+/// With the `chrono` feature all strftime specifiers are supported,
+/// otherwise the built-in calendar supports `%Y`, `%m`, `%d`, `%H`, `%M`, `%S` and `%%`.
+///
+///```rust
+/// use log::LevelFilter;
+/// use trivial_log::DatedFileAppender;
+/// let template = std::env::temp_dir().join("trivial_log_dated_doc-%Y-%m-%d.log");
+/// trivial_log::builder()
+///   .default_format(|builder| {
+///     builder.appender_filter(LevelFilter::Info, DatedFileAppender::new(template.to_str().unwrap()).unwrap())
+///   })
+///   .init()
+///   .unwrap();
+/// log::info!("Hello");
+/// trivial_log::free();
+///```
+pub struct DatedFileAppender {
+  /// The strftime template of the path
+  template: Template,
+  /// The current file, `None` if the next one could not be opened
+  current: Mutex<Option<Current>>,
+  /// Number of messages that could not be written
+  dropped: AtomicU64,
+}
+
+impl DatedFileAppender {
+  /// Opens (or creates) the file for the current time for appending.
+  /// # Errors
+  /// If the template is invalid or the file cannot be opened.
+  pub fn new(template: impl Into<String>) -> io::Result<Self> {
+    let template = template.into();
+    let parsed = Template::parse(&template).ok_or_else(|| {
+      io::Error::new(io::ErrorKind::InvalidInput, format!("invalid path template `{template}`"))
+    })?;
+    let secs = unix_secs(SystemTime::now());
+    let path = parsed.expand(secs).ok_or_else(|| io::Error::other("time out of range"))?;
+    let current = Self::open_path(path, secs)?;
+    Ok(Self { template: parsed, current: Mutex::new(Some(current)), dropped: AtomicU64::new(0) })
+  }
+
+  /// Returns the path of the file that is currently written to.
+  pub fn path(&self) -> Option<PathBuf> {
+    let current = self.current.lock().ok()?;
+    current.as_ref().map(|current| PathBuf::from(&current.path))
+  }
+
+  /// Opens the file at the expanded `path` for appending, creating missing directories.
+  fn open_path(path: String, secs: i64) -> io::Result<Current> {
+    if let Some(parent) = Path::new(&path).parent() {
+      if !parent.as_os_str().is_empty() {
+        fs::create_dir_all(parent)?;
+      }
+    }
+    let file = OpenOptions::new().append(true).create(true).open(&path)?;
+    Ok(Current { file, path, secs })
+  }
+
+  /// Writes the message to the file for `time`, switching files first if required.
+  fn write(&self, time: SystemTime, message: &[u8]) -> io::Result<()> {
+    let mut current = self.current.lock().map_err(|_| io::Error::other("poisoned"))?;
+    self.write_locked(&mut current, unix_secs(time), message)
+  }
+
+  /// Writes the message to the locked file, switching files first if required.
+  fn write_locked(
+    &self,
+    current: &mut Option<Current>,
+    secs: i64,
+    message: &[u8],
+  ) -> io::Result<()> {
+    // The template is expanded at most once per second.
+    let path = match current.as_ref() {
+      Some(open) if secs <= open.secs => None,
+      _ => {
+        let path =
+          self.template.expand(secs).ok_or_else(|| io::Error::other("time out of range"))?;
+        current.as_ref().is_none_or(|open| open.path != path).then_some(path)
+      }
+    };
+
+    match path {
+      Some(path) => {
+        // The previous file is closed before the next one is opened.
+        if let Some(previous) = current.take() {
+          _ = previous.file.sync_all();
+        }
+        *current = Some(Self::open_path(path, secs)?);
+      }
+      None => {
+        if let Some(open) = current.as_mut() {
+          open.secs = open.secs.max(secs);
+        }
+      }
+    }
+
+    let Some(current) = current.as_mut() else {
+      return Err(io::Error::other("no file"));
+    };
+    current.file.write_all(message)
+  }
+
+  /// Writes the message, counting it as dropped on errors.
+  fn append(&self, time: SystemTime, message: &[u8]) {
+    if self.write(time, message).is_err() {
+      self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+}
+
+impl<T: AsRef<[u8]>> Appender<T> for DatedFileAppender {
+  fn append_log_message(&self, message: &T) {
+    self.append(SystemTime::now(), message.as_ref());
+  }
+
+  fn append_log_context(&self, ctx: &LogContext<'_>, _record: &Record<'_>, message: &T) {
+    self.append(ctx.now(), message.as_ref());
+  }
+
+  fn flush(&self) {
+    if let Ok(mut current) = self.current.lock() {
+      if let Some(current) = current.as_mut() {
+        _ = current.file.flush();
+      }
+    }
+  }
+
+  fn dropped(&self) -> u64 {
+    self.dropped.load(Ordering::Relaxed)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::fs;
+  use std::io;
+  use std::sync::Arc;
+  use std::time::{Duration, Instant, UNIX_EPOCH};
+
+  use log::{Level, Record};
+
+  use crate::{
+    Appender, BytesAdapter, DatedFileAppender, Dedup, IntoAppender, LogContext, RateLimited,
+    RingBufferAppender, Sampled,
+  };
+
+  #[test]
+  fn switches_files() {
+    let dir = std::env::temp_dir().join(format!("trivial_log_dated_{}", std::process::id()));
+    _ = fs::remove_dir_all(&dir);
+    let template = dir.join("logs").join("app-%Y-%m-%d.log");
+    let appender = DatedFileAppender::new(template.to_str().expect("utf-8")).expect("open");
+
+    let extensions = Vec::new();
+    let record = Record::builder().build();
+    // 2099-12-31T23:59:59Z, then a second later and a late record of the previous day.
+    let before = UNIX_EPOCH + Duration::from_secs(4_102_444_799);
+    for (now, msg) in
+      [(before, "one\n"), (before + Duration::from_secs(1), "two\n"), (before, "three\n")]
+    {
+      let ctx = LogContext::new(now, 0, Instant::now(), None, &extensions);
+      appender.append_log_context(&ctx, &record, &msg);
+    }
+
+    let logs = dir.join("logs");
+    assert_eq!(fs::read_to_string(logs.join("app-2099-12-31.log")).expect("read"), "one\n");
+    assert_eq!(fs::read_to_string(logs.join("app-2100-01-01.log")).expect("read"), "two\nthree\n");
+    assert_eq!(appender.path(), Some(logs.join("app-2100-01-01.log")));
+    assert_eq!(Appender::<&str>::dropped(&appender), 0);
+    _ = fs::remove_dir_all(&dir);
+
+    for invalid in ["%Q", "app-%"] {
+      let err = DatedFileAppender::new(dir.join(invalid).to_str().expect("utf-8")).err();
+      assert_eq!(err.map(|err| err.kind()), Some(io::ErrorKind::InvalidInput), "{invalid}");
+    }
+    assert!(!dir.exists());
+  }
+
+  /// Wrappers forward the time of the record.
+  #[test]
+  fn wrapped() {
+    let dir =
+      std::env::temp_dir().join(format!("trivial_log_dated_wrapped_{}", std::process::id()));
+    _ = fs::remove_dir_all(&dir);
+    let dated = |name: &str| {
+      let template = dir.join(format!("{name}-%Y.log"));
+      DatedFileAppender::new(template.to_str().expect("utf-8")).expect("open")
+    };
+
+    let wrappers: [(&str, Arc<dyn Appender<String>>); 5] = [
+      (
+        "rate_limited",
+        RateLimited::new(dated("rate_limited"), 10, Duration::from_secs(1)).into_appender(),
+      ),
+      ("dedup", Dedup::new(dated("dedup")).into_appender()),
+      ("sampled", Sampled::new(dated("sampled")).into_appender()),
+      (
+        "ring_buffer",
+        RingBufferAppender::new(4).dump_on_error(dated("ring_buffer")).into_appender(),
+      ),
+      ("bytes", BytesAdapter::new(dated("bytes")).into_appender()),
+    ];
+
+    let extensions = Vec::new();
+    let ctx = LogContext::new(
+      UNIX_EPOCH + Duration::from_secs(4_102_444_801),
+      0,
+      Instant::now(),
+      None,
+      &extensions,
+    );
+    let record = Record::builder().level(Level::Error).build();
+    for (name, wrapper) in wrappers {
+      wrapper.append_log_context(&ctx, &record, &"message\n".to_string());
+      assert_eq!(
+        fs::read_to_string(dir.join(format!("{name}-2100.log"))).ok().as_deref(),
+        Some("message\n"),
+        "{name}"
+      );
+    }
+    _ = fs::remove_dir_all(&dir);
+  }
+}
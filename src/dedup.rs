@@ -0,0 +1,273 @@
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use log::{Level, Record};
+
+use crate::{util, Appender, FormatFn, IntoAppender, LogContext};
+
+/// The last record that was forwarded and how often it was repeated since.
+struct Last {
+  /// Level of the record
+  level: Level,
+  /// Target of the record
+  target: String,
+  /// The formatted arguments of the record
+  args: String,
+  /// Number of suppressed repetitions
+  repeated: u64,
+}
+
+/// Appender wrapper that collapses consecutive identical messages, similar to syslog's
+/// "last message repeated N times".
+///
+/// Messages are compared by the level, target and arguments of their records,
+/// so the timestamp, sequence number or colors added by the format don't matter.
+/// The summary is sent to the wrapped appender before the next distinct message,
+/// when the appender is flushed or when `trivial_log::free()` is called.
+pub struct Dedup {
+  /// The wrapped appender
+  inner: Arc<dyn Appender<String>>,
+  /// Format fn for the synthesized summary records
+  summary: Box<FormatFn<String>>,
+  /// The last forwarded message
+  last: Mutex<Option<Last>>,
+}
+
+impl Dedup {
+  /// Wraps `inner`.
+  /// Summary records are formatted using the default format.
+  #[must_use]
+  pub fn new(inner: impl IntoAppender<String>) -> Self {
+    Self::with_summary_format(inner, util::default_format)
+  }
+
+  /// Wraps `inner`.
+  /// The given format fn, usually the one of the handler, is used to turn the summary record into a message.
+  #[must_use]
+  pub fn with_summary_format(
+    inner: impl IntoAppender<String>,
+    summary: impl Fn(SystemTime, &Record<'_>) -> Option<String> + Send + Sync + 'static,
+  ) -> Self {
+    Self { inner: inner.into_appender(), summary: Box::new(summary), last: Mutex::new(None) }
+  }
+
+  /// Sends the summary for `last` if it was repeated.
+  fn emit_summary(&self, last: &mut Last) {
+    let repeated = std::mem::take(&mut last.repeated);
+    if repeated == 0 {
+      return;
+    }
+
+    let times = if repeated == 1 { "time" } else { "times" };
+    self.emit_record(
+      last.level,
+      &last.target,
+      format_args!("last message repeated {repeated} {times}"),
+    );
+  }
+
+  /// Formats a synthesized record with the summary format and sends it to the wrapped appender.
+  fn emit_record(&self, level: Level, target: &str, args: fmt::Arguments<'_>) {
+    let record = Record::builder().level(level).target(target).args(args).build();
+    if let Some(summary) = (self.summary)(SystemTime::now(), &record) {
+      self.inner.append_log_record(&record, &summary);
+    }
+  }
+
+  /// Forwards the message unless its record repeats the last one.
+  fn append(&self, ctx: Option<&LogContext<'_>>, record: &Record<'_>, message: &String) {
+    let Ok(mut guard) = self.last.lock() else {
+      return;
+    };
+
+    if let Some(last) = guard.as_mut() {
+      if last.level == record.level()
+        && last.target == record.target()
+        && same_args(&last.args, record.args())
+      {
+        last.repeated += 1;
+        return;
+      }
+
+      self.emit_summary(last);
+      last.level = record.level();
+      last.target.clear();
+      last.target.push_str(record.target());
+      last.args.clear();
+      _ = fmt::write(&mut last.args, *record.args());
+    } else {
+      *guard = Some(Last {
+        level: record.level(),
+        target: record.target().to_string(),
+        args: record.args().to_string(),
+        repeated: 0,
+      });
+    }
+
+    // The lock is held while forwarding so the summary can never overtake the message.
+    util::forward(self.inner.as_ref(), ctx, record, message);
+  }
+}
+
+/// Returns true if `args` formats to `expected`, without allocating.
+fn same_args(expected: &str, args: &fmt::Arguments<'_>) -> bool {
+  /// Consumes `expected` while the written text matches it.
+  struct Matches<'a>(&'a str);
+
+  impl fmt::Write for Matches<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+      self.0 = self.0.strip_prefix(s).ok_or(fmt::Error)?;
+      Ok(())
+    }
+  }
+
+  let mut matches = Matches(expected);
+  fmt::write(&mut matches, *args).is_ok() && matches.0.is_empty()
+}
+
+impl Appender<String> for Dedup {
+  fn append_log_message(&self, message: &String) {
+    // Without a record the whole message is compared.
+    self.append_log_record(&Record::builder().args(format_args!("{message}")).build(), message);
+  }
+
+  fn append_log_record(&self, record: &Record<'_>, message: &String) {
+    self.append(None, record, message);
+  }
+
+  fn append_log_context(&self, ctx: &LogContext<'_>, record: &Record<'_>, message: &String) {
+    self.append(Some(ctx), record, message);
+  }
+
+  fn flush(&self) {
+    if let Ok(mut guard) = self.last.lock() {
+      if let Some(last) = guard.as_mut() {
+        self.emit_summary(last);
+      }
+    }
+
+    self.inner.flush();
+  }
+
+  fn dump(&self, out: &mut dyn io::Write) {
+    self.inner.dump(out);
+  }
+
+  fn dropped(&self) -> u64 {
+    // Repetitions are summarized, not dropped.
+    self.inner.dropped()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::sync::{Arc, Mutex};
+  use std::time::{Instant, UNIX_EPOCH};
+
+  use log::{Level, Record};
+
+  use crate::log_context::Extensions;
+  use crate::{Appender, Dedup, DefaultFormatOptions, LogContext};
+
+  /// Returns a `Dedup` that collects the forwarded messages.
+  fn collecting() -> (Dedup, Arc<Mutex<Vec<String>>>) {
+    let out = Arc::new(Mutex::new(Vec::new()));
+    let out2 = Arc::clone(&out);
+    (Dedup::new(move |msg: &String| out2.lock().expect("poisoned").push(msg.clone())), out)
+  }
+
+  #[test]
+  fn collapse() {
+    let (dedup, out) = collecting();
+
+    for (time, msg) in [(1, "same"), (2, "same"), (3, "same"), (4, "other"), (5, "other")] {
+      dedup.append_log_record(
+        &Record::builder().level(Level::Error).args(format_args!("{msg}")).build(),
+        &format!("[E] - {time} - T - {msg}\n"),
+      );
+    }
+
+    let got = out.lock().expect("poisoned").clone();
+    assert_eq!(got.len(), 3);
+    assert_eq!(got.first().map(String::as_str), Some("[E] - 1 - T - same\n"));
+    assert!(got.get(1).is_some_and(|s| s.ends_with(" - last message repeated 2 times\n")));
+    assert_eq!(got.get(2).map(String::as_str), Some("[E] - 4 - T - other\n"));
+
+    dedup.flush();
+    let got = out.lock().expect("poisoned").clone();
+    assert_eq!(got.len(), 4);
+    assert!(got.get(3).is_some_and(|s| s.ends_with(" - last message repeated 1 time\n")));
+  }
+
+  #[test]
+  fn level_and_target() {
+    let (dedup, out) = collecting();
+
+    for (level, target) in [(Level::Error, "a"), (Level::Warn, "a"), (Level::Warn, "b")] {
+      let record = Record::builder().level(level).target(target).args(format_args!("same")).build();
+      dedup.append_log_record(&record, &"same".to_string());
+    }
+
+    assert_eq!(out.lock().expect("poisoned").len(), 3);
+  }
+
+  #[test]
+  fn color_and_sequence() {
+    let (dedup, out) = collecting();
+    let options = DefaultFormatOptions::new().color(true).sequence(true);
+    let extensions = Extensions::new();
+
+    for sequence in 0..3 {
+      let ctx = LogContext::new(UNIX_EPOCH, sequence, Instant::now(), None, &extensions);
+      let record = Record::builder().level(Level::Error).args(format_args!("oops")).build();
+      let mut msg = String::new();
+      options.format_with_context(&ctx, &record, &mut msg).expect("format");
+      dedup.append_log_context(&ctx, &record, &msg);
+    }
+    dedup.flush();
+
+    let got = out.lock().expect("poisoned").clone();
+    assert_eq!(got.len(), 2);
+    assert!(got.first().is_some_and(|s| s.starts_with("\x1b[31m[E]\x1b[0m - #0 - ")));
+    assert!(got.get(1).is_some_and(|s| s.ends_with(" - last message repeated 2 times\n")));
+  }
+
+  #[test]
+  fn without_record() {
+    let (dedup, out) = collecting();
+
+    for msg in ["a", "a", "b"] {
+      dedup.append_log_message(&msg.to_string());
+    }
+
+    let got = out.lock().expect("poisoned").clone();
+    assert_eq!(got.len(), 3);
+    assert_eq!(got.first().map(String::as_str), Some("a"));
+    assert!(got.get(1).is_some_and(|s| s.ends_with(" - last message repeated 1 time\n")));
+    assert_eq!(got.get(2).map(String::as_str), Some("b"));
+  }
+
+  #[test]
+  fn summary_format() {
+    let out = Arc::new(Mutex::new(Vec::new()));
+    let out2 = Arc::clone(&out);
+    let dedup = Dedup::with_summary_format(
+      move |msg: &String| out2.lock().expect("poisoned").push(msg.clone()),
+      |_, record| Some(format!("{} {}: {}\n", record.level(), record.target(), record.args())),
+    );
+
+    let record =
+      Record::builder().level(Level::Warn).target("app").args(format_args!("disk full")).build();
+    for _ in 0..3 {
+      dedup.append_log_record(&record, &"WARN app: disk full\n".to_string());
+    }
+    dedup.flush();
+
+    assert_eq!(
+      *out.lock().expect("poisoned"),
+      ["WARN app: disk full\n", "WARN app: last message repeated 2 times\n"]
+    );
+  }
+}
@@ -6,6 +6,13 @@ use std::fmt::Display;
 pub enum Error {
   /// `log` already initialized by another crate
   AlreadyInitialized,
+  /// A configuration could not be read, parsed or applied
+  Config {
+    /// Line of the configuration file the error refers to, starting at 1
+    line: Option<usize>,
+    /// What is wrong
+    message: String,
+  },
 }
 
 impl error::Error for Error {}
@@ -14,6 +21,8 @@ impl Display for Error {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     match self {
       Self::AlreadyInitialized => write!(f, "`log` has already been initialized by another crate"),
+      Self::Config { line: Some(line), message } => write!(f, "config line {line}: {message}"),
+      Self::Config { line: None, message } => write!(f, "config: {message}"),
     }
   }
 }
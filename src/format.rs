@@ -0,0 +1,544 @@
+use std::fmt::{self, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::{Level, Record};
+
+use crate::calendar::CivilDateTime;
+use crate::{context, util, LogContext};
+
+/// Time zone used for timestamps of the default format.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum TimeZone {
+  /// Coordinated Universal Time
+  #[default]
+  Utc,
+  /// The local time zone of the machine. Requires the `chrono` feature, falls back to UTC otherwise.
+  Local,
+  /// A fixed offset in seconds east of UTC. Offsets of a day or more fall back to UTC.
+  FixedOffset(i32),
+}
+
+/// How the default format renders the timestamp.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub enum TimestampFormat {
+  /// `17 Feb 2025 22:27:20.956 UTC`, the offset is printed instead of `UTC` for other time zones.
+  #[default]
+  Default,
+  /// `2025-02-17T22:27:20.956Z` or `2025-02-17T22:27:20.956+01:00`
+  Rfc3339,
+  /// `2025-02-17T22:27:20.956+00:00`, always with a numeric offset.
+  Iso8601,
+  /// Milliseconds since the unix epoch, `1739831240956`
+  EpochMillis,
+  /// A custom strftime format string. Requires the `chrono` feature, falls back to `EpochMillis` otherwise
+  /// and if the format string is invalid.
+  Strftime(String),
+  /// Time since `Builder::init` instead of the wall clock, `+0.123456s`.
+  /// Falls back to `Default` when formatting without the logger (`format` and `format_into`).
+  Uptime,
+}
+
+/// How the default format renders the thread that logged the record.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum ThreadFormat {
+  /// `ThreadId(7)`
+  #[default]
+  Id,
+  /// The name of the thread, `main` for example. Unnamed threads fall back to `Id`.
+  Name,
+  /// The kernel thread id as shown by `top -H`, `ps -L` or perf. Falls back to `Id` on platforms other than Linux.
+  OsId,
+  /// The name of the thread (or `Id` if unnamed) followed by the kernel thread id in parentheses, `main(4711)`.
+  NameAndOsId,
+}
+
+/// Options for the default format.
+/// The default value produces the same output as `Builder::default_format`.
+///
+///```rust
+/// use trivial_log::{DefaultFormatOptions, TimeZone, TimestampFormat};
+/// let options = DefaultFormatOptions::new().time_zone(TimeZone::Local).timestamp(TimestampFormat::Rfc3339);
+///```
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+#[expect(clippy::struct_excessive_bools, reason = "independent on/off switches of the builder")]
+pub struct DefaultFormatOptions {
+  /// Time zone of the timestamp
+  time_zone: TimeZone,
+  /// Format of the timestamp
+  timestamp: TimestampFormat,
+  /// The parsed `TimestampFormat::Strftime`, `None` if it is invalid
+  #[cfg(feature = "chrono")]
+  strftime: Option<Vec<chrono::format::Item<'static>>>,
+  /// Format of the thread
+  thread: ThreadFormat,
+  /// Print the target of the record
+  target: bool,
+  /// Print the `file:line` of the record
+  location: bool,
+  /// Color the level prefix with ANSI escape codes
+  color: bool,
+  /// Print the sequence number of the record
+  sequence: bool,
+  /// Print the time since the previous record of the thread
+  delta: bool,
+}
+
+impl DefaultFormatOptions {
+  /// Options that produce the output of `Builder::default_format`
+  pub(crate) const DEFAULT: Self = Self {
+    time_zone: TimeZone::Utc,
+    timestamp: TimestampFormat::Default,
+    #[cfg(feature = "chrono")]
+    strftime: None,
+    thread: ThreadFormat::Id,
+    target: false,
+    location: false,
+    color: false,
+    sequence: false,
+    delta: false,
+  };
+
+  /// Options for RFC 3339 timestamps in UTC
+  pub(crate) const RFC3339: Self = Self { timestamp: TimestampFormat::Rfc3339, ..Self::DEFAULT };
+
+  /// Creates the default options.
+  #[must_use]
+  pub const fn new() -> Self {
+    Self::DEFAULT
+  }
+
+  /// Sets the time zone of the timestamp. Defaults to `TimeZone::Utc`.
+  /// A `TimeZone::FixedOffset` of a day or more is replaced with `TimeZone::Utc`.
+  #[must_use]
+  pub const fn time_zone(mut self, time_zone: TimeZone) -> Self {
+    self.time_zone = match time_zone {
+      TimeZone::FixedOffset(secs) if secs <= -86_400 || secs >= 86_400 => TimeZone::Utc,
+      time_zone => time_zone,
+    };
+    self
+  }
+
+  /// Sets the format of the timestamp. Defaults to `TimestampFormat::Default`.
+  /// A strftime format string is parsed once here, not for every record.
+  #[must_use]
+  pub fn timestamp(mut self, timestamp: TimestampFormat) -> Self {
+    #[cfg(feature = "chrono")]
+    {
+      self.strftime = match &timestamp {
+        TimestampFormat::Strftime(fmt) => {
+          chrono::format::StrftimeItems::new(fmt).parse_to_owned().ok()
+        }
+        _ => None,
+      };
+    }
+    self.timestamp = timestamp;
+    self
+  }
+
+  /// Sets how the thread is printed. Defaults to `ThreadFormat::Id`.
+  #[must_use]
+  pub const fn thread(mut self, thread: ThreadFormat) -> Self {
+    self.thread = thread;
+    self
+  }
+
+  /// Print the target of the record (usually the module path) after the thread. Defaults to false.
+  #[must_use]
+  pub const fn target(mut self, target: bool) -> Self {
+    self.target = target;
+    self
+  }
+
+  /// Print the `file:line` of the log statement after the thread and target. Defaults to false.
+  #[must_use]
+  pub const fn location(mut self, location: bool) -> Self {
+    self.location = location;
+    self
+  }
+
+  /// Color the `[E]`/`[W]`/`[I]`/`[D]`/`[T]` prefix with ANSI escape codes. Defaults to false.
+  ///
+  /// This colors unconditionally, see `Builder::colored_default_format` and `StdOptions::color`
+  /// for variants that respect `NO_COLOR`/`CLICOLOR_FORCE` and detect terminals.
+  #[must_use]
+  pub const fn color(mut self, color: bool) -> Self {
+    self.color = color;
+    self
+  }
+
+  /// Print the sequence number of the record as `#42` after the level prefix. Defaults to false.
+  /// Gaps in the numbers show that records were dropped between the logger and the reader.
+  ///
+  /// Only records formatted by the logger or with `format_with_context` have a sequence number,
+  /// `format` and `format_into` print nothing for it.
+  #[must_use]
+  pub const fn sequence(mut self, sequence: bool) -> Self {
+    self.sequence = sequence;
+    self
+  }
+
+  /// Print the time since the previous record of the same thread as `(+0.000123s)` after the timestamp. Defaults to false.
+  ///
+  /// Like the sequence number this needs the logger, `format` and `format_into` print nothing for it.
+  #[must_use]
+  pub const fn delta(mut self, delta: bool) -> Self {
+    self.delta = delta;
+    self
+  }
+
+  /// Formats the record using these options.
+  #[must_use]
+  pub fn format(&self, now: SystemTime, record: &Record<'_>) -> Option<String> {
+    let mut buf = String::with_capacity(128);
+    self.format_into(now, record, &mut buf).ok()?;
+    Some(buf)
+  }
+
+  /// Formats the record using these options and appends it to `buf`.
+  /// This does not allocate unless `buf` needs to grow.
+  /// # Errors
+  /// If the timestamp cannot be represented or a `Display` impl of the message fails.
+  pub fn format_into(&self, now: SystemTime, record: &Record<'_>, buf: &mut String) -> fmt::Result {
+    self.write(now, None, record, buf)
+  }
+
+  /// Formats the record using these options and the context passed to format fns, and appends it to `buf`.
+  /// This does not allocate unless `buf` needs to grow.
+  /// # Errors
+  /// If the timestamp cannot be represented or a `Display` impl of the message fails.
+  pub fn format_with_context(
+    &self,
+    ctx: &LogContext<'_>,
+    record: &Record<'_>,
+    buf: &mut String,
+  ) -> fmt::Result {
+    self.write(ctx.now(), Some(ctx), record, buf)
+  }
+
+  /// Writes the record to `buf`, values that need the context are only printed if it is known.
+  fn write(
+    &self,
+    now: SystemTime,
+    ctx: Option<&LogContext<'_>>,
+    record: &Record<'_>,
+    buf: &mut String,
+  ) -> fmt::Result {
+    let prefix = match (record.metadata().level(), self.color) {
+      (Level::Error, false) => "[E]",
+      (Level::Warn, false) => "[W]",
+      (Level::Info, false) => "[I]",
+      (Level::Debug, false) => "[D]",
+      (Level::Trace, false) => "[T]",
+      (Level::Error, true) => "\x1b[31m[E]\x1b[0m",
+      (Level::Warn, true) => "\x1b[33m[W]\x1b[0m",
+      (Level::Info, true) => "\x1b[32m[I]\x1b[0m",
+      (Level::Debug, true) => "\x1b[35m[D]\x1b[0m",
+      (Level::Trace, true) => "\x1b[37m[T]\x1b[0m",
+    };
+
+    buf.push_str(prefix);
+    buf.push_str(" - ");
+    match ctx {
+      Some(ctx) => {
+        if self.sequence {
+          write!(buf, "#{} - ", ctx.sequence())?;
+        }
+        if self.timestamp == TimestampFormat::Uptime {
+          write_elapsed(buf, ctx.uptime())?;
+        } else {
+          self.write_timestamp(buf, now).ok_or(fmt::Error)?;
+        }
+        if self.delta {
+          buf.push_str(" (");
+          write_elapsed(buf, ctx.delta())?;
+          buf.push(')');
+        }
+      }
+      None => self.write_timestamp(buf, now).ok_or(fmt::Error)?,
+    }
+    buf.push_str(" - ");
+    self.write_thread(buf).ok_or(fmt::Error)?;
+
+    if self.target {
+      buf.push_str(" - ");
+      buf.push_str(record.target());
+    }
+
+    if self.location {
+      write!(buf, " - {}:{}", record.file().unwrap_or("<unknown>"), record.line().unwrap_or(0))?;
+    }
+
+    let mut separator = " - ";
+    let mut write_field = |key: &str, value: &str| {
+      buf.push_str(separator);
+      buf.push_str(key);
+      buf.push('=');
+      buf.push_str(value);
+      separator = " ";
+    };
+    if let Some(ctx) = ctx {
+      ctx.for_each_global_field(&mut write_field);
+    }
+    context::for_each(write_field);
+
+    writeln!(buf, " - {}", record.args())
+  }
+
+  /// Writes the current thread to `buf`.
+  fn write_thread(&self, buf: &mut String) -> Option<()> {
+    let thread = std::thread::current();
+    let name_or_id = |buf: &mut String| match thread.name() {
+      Some(name) => buf.write_str(name),
+      None => write!(buf, "{:?}", thread.id()),
+    };
+
+    let result = match self.thread {
+      ThreadFormat::Id => write!(buf, "{:?}", thread.id()),
+      ThreadFormat::Name => name_or_id(buf),
+      ThreadFormat::OsId => match util::os_thread_id() {
+        Some(tid) => write!(buf, "{tid}"),
+        None => write!(buf, "{:?}", thread.id()),
+      },
+      ThreadFormat::NameAndOsId => name_or_id(buf)
+        .and_then(|()| util::os_thread_id().map_or(Ok(()), |tid| write!(buf, "({tid})"))),
+    };
+
+    result.ok()
+  }
+
+  /// Writes only the timestamp for `now` to `buf`.
+  pub(crate) fn write_time(&self, now: SystemTime, buf: &mut String) -> fmt::Result {
+    self.write_timestamp(buf, now).ok_or(fmt::Error)
+  }
+
+  /// Writes the timestamp for `now` to `buf`.
+  fn write_timestamp(&self, buf: &mut String, now: SystemTime) -> Option<()> {
+    let millis = now.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis());
+    let millis = i64::try_from(millis).unwrap_or(0);
+
+    #[cfg(feature = "chrono")]
+    if let Some(items) = &self.strftime {
+      return self.write_strftime(buf, millis, items);
+    }
+
+    // The built-in calendar does not allocate, unlike formatting with chrono.
+    let (offset, utc) = match self.time_zone {
+      TimeZone::Utc => (0, true),
+      #[cfg(feature = "chrono")]
+      TimeZone::Local => {
+        use chrono::Offset;
+        let utc = chrono::DateTime::from_timestamp_millis(millis)?;
+        (utc.with_timezone(&chrono::Local).offset().fix().local_minus_utc(), false)
+      }
+      #[cfg(not(feature = "chrono"))]
+      TimeZone::Local => (0, true),
+      TimeZone::FixedOffset(secs) => (secs, false),
+    };
+
+    let dt = CivilDateTime::from_unix_millis(millis, offset);
+    let result = match &self.timestamp {
+      TimestampFormat::Default | TimestampFormat::Uptime => dt.write_default(buf, utc),
+      TimestampFormat::Rfc3339 => dt.write_rfc3339(buf, utc),
+      TimestampFormat::Iso8601 => dt.write_rfc3339(buf, false),
+      TimestampFormat::EpochMillis | TimestampFormat::Strftime(_) => write!(buf, "{millis}"),
+    };
+
+    result.ok()
+  }
+
+  /// Writes the timestamp using a strftime format string.
+  #[cfg(feature = "chrono")]
+  fn write_strftime(
+    &self,
+    buf: &mut String,
+    millis: i64,
+    items: &[chrono::format::Item<'static>],
+  ) -> Option<()> {
+    let utc = chrono::DateTime::from_timestamp_millis(millis)?;
+    let result = match self.time_zone {
+      TimeZone::Utc => write!(buf, "{}", utc.format_with_items(items.iter())),
+      TimeZone::Local => {
+        write!(buf, "{}", utc.with_timezone(&chrono::Local).format_with_items(items.iter()))
+      }
+      TimeZone::FixedOffset(secs) => {
+        let offset = chrono::FixedOffset::east_opt(secs)?;
+        write!(buf, "{}", utc.with_timezone(&offset).format_with_items(items.iter()))
+      }
+    };
+
+    result.ok()
+  }
+}
+
+/// Writes a duration as `+1.234567s`.
+pub fn write_elapsed(buf: &mut String, elapsed: Duration) -> fmt::Result {
+  write!(buf, "+{}.{:06}s", elapsed.as_secs(), elapsed.subsec_micros())
+}
+
+#[cfg(test)]
+mod test {
+  use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+  use log::{Level, Record};
+
+  use crate::{DefaultFormatOptions, LogContext, ThreadFormat, TimeZone, TimestampFormat};
+
+  fn timestamp(options: &DefaultFormatOptions) -> String {
+    let now: SystemTime = UNIX_EPOCH + Duration::from_millis(1_739_831_240_956);
+    let mut buf = String::new();
+    options.write_timestamp(&mut buf, now).expect("format failed");
+    buf
+  }
+
+  #[test]
+  fn timestamps() {
+    let utc = DefaultFormatOptions::new();
+    assert_eq!(timestamp(&utc), "17 Feb 2025 22:27:20.956 UTC");
+    assert_eq!(
+      timestamp(&utc.clone().timestamp(TimestampFormat::Rfc3339)),
+      "2025-02-17T22:27:20.956Z"
+    );
+    assert_eq!(
+      timestamp(&utc.clone().timestamp(TimestampFormat::Iso8601)),
+      "2025-02-17T22:27:20.956+00:00"
+    );
+    #[cfg(feature = "chrono")]
+    assert_eq!(
+      timestamp(&utc.clone().timestamp(TimestampFormat::Strftime("%Y/%m/%d %H".to_string()))),
+      "2025/02/17 22"
+    );
+    assert_eq!(
+      timestamp(&utc.clone().timestamp(TimestampFormat::Strftime("%Q".to_string()))),
+      "1739831240956"
+    );
+    assert_eq!(timestamp(&utc.timestamp(TimestampFormat::EpochMillis)), "1739831240956");
+
+    let offset = DefaultFormatOptions::new().time_zone(TimeZone::FixedOffset(3600 + 1800));
+    assert_eq!(timestamp(&offset), "17 Feb 2025 23:57:20.956 +01:30");
+    assert_eq!(
+      timestamp(&offset.timestamp(TimestampFormat::Rfc3339)),
+      "2025-02-17T23:57:20.956+01:30"
+    );
+
+    let invalid = DefaultFormatOptions::new().time_zone(TimeZone::FixedOffset(-86_400));
+    assert_eq!(timestamp(&invalid), "17 Feb 2025 22:27:20.956 UTC");
+    #[cfg(feature = "chrono")]
+    assert_eq!(
+      timestamp(&invalid.timestamp(TimestampFormat::Strftime("%H:%M %z".to_string()))),
+      "22:27 +0000"
+    );
+  }
+
+  #[test]
+  fn target_and_location() {
+    let record = Record::builder()
+      .level(Level::Warn)
+      .target("my_crate::db")
+      .file(Some("src/db.rs"))
+      .line(Some(42))
+      .args(format_args!("slow query"))
+      .build();
+
+    let options = DefaultFormatOptions::new().target(true).location(true);
+    let msg = options.format(UNIX_EPOCH, &record).expect("format failed");
+    assert!(msg.starts_with("[W] - "), "{msg}");
+    assert!(msg.ends_with(" - my_crate::db - src/db.rs:42 - slow query\n"), "{msg}");
+
+    let msg = options.clone().target(false).format(UNIX_EPOCH, &record).expect("format failed");
+    assert!(msg.ends_with(") - src/db.rs:42 - slow query\n"), "{msg}");
+
+    let msg = crate::context::scope(&[("request_id", "abc"), ("user", "7")], || {
+      options.format(UNIX_EPOCH, &record).expect("format failed")
+    });
+    assert!(msg.ends_with(" - src/db.rs:42 - request_id=abc user=7 - slow query\n"), "{msg}");
+  }
+
+  #[test]
+  fn color() {
+    let record = Record::builder().level(Level::Error).args(format_args!("boom")).build();
+
+    let msg = DefaultFormatOptions::new().color(true).format(UNIX_EPOCH, &record).expect("format");
+    assert!(msg.starts_with("\x1b[31m[E]\x1b[0m - 01 Jan 1970"), "{msg}");
+    assert!(msg.ends_with(" - boom\n"), "{msg}");
+
+    let msg = DefaultFormatOptions::new().format(UNIX_EPOCH, &record).expect("format");
+    assert!(msg.starts_with("[E] - 01 Jan 1970"), "{msg}");
+  }
+
+  #[test]
+  fn sequence() {
+    let record = Record::builder().level(Level::Info).args(format_args!("next")).build();
+    let extensions = Vec::new();
+    let ctx = LogContext::new(UNIX_EPOCH, 42, Instant::now(), None, &extensions);
+    let options = DefaultFormatOptions::new().sequence(true);
+
+    let mut msg = String::new();
+    options.format_with_context(&ctx, &record, &mut msg).expect("format");
+    assert!(msg.starts_with("[I] - #42 - 01 Jan 1970"), "{msg}");
+
+    let msg = options.format(UNIX_EPOCH, &record).expect("format");
+    assert!(msg.starts_with("[I] - 01 Jan 1970"), "{msg}");
+
+    let mut msg = String::new();
+    DefaultFormatOptions::new().format_with_context(&ctx, &record, &mut msg).expect("format");
+    assert!(msg.starts_with("[I] - 01 Jan 1970"), "{msg}");
+  }
+
+  /// Returns how `format` prints a thread with the given name, or an unnamed one.
+  fn thread_of(name: Option<&str>, format: ThreadFormat) -> String {
+    let builder = name.map_or_else(std::thread::Builder::new, |name| {
+      std::thread::Builder::new().name(name.to_string())
+    });
+    let options = DefaultFormatOptions::new().thread(format);
+    let thread = builder.spawn(move || {
+      let mut buf = String::new();
+      options.write_thread(&mut buf).expect("format failed");
+      buf
+    });
+    thread.expect("spawn").join().expect("thread panicked")
+  }
+
+  #[test]
+  fn threads() {
+    assert_eq!(thread_of(Some("worker"), ThreadFormat::Name), "worker");
+    let unnamed = thread_of(None, ThreadFormat::Name);
+    assert!(unnamed.starts_with("ThreadId(") && unnamed.ends_with(')'), "{unnamed}");
+
+    let named = thread_of(Some("worker"), ThreadFormat::NameAndOsId);
+    let unnamed = thread_of(None, ThreadFormat::NameAndOsId);
+    if cfg!(target_os = "linux") {
+      let tid = |rest: Option<&str>| {
+        rest.and_then(|rest| rest.strip_suffix(')')).is_some_and(|tid| tid.parse::<u64>().is_ok())
+      };
+      assert!(tid(named.strip_prefix("worker(")), "{named}");
+      assert!(
+        unnamed.starts_with("ThreadId(") && tid(unnamed.split_once(")(").map(|(_, tid)| tid))
+      );
+    } else {
+      assert_eq!(named, "worker");
+      assert!(unnamed.starts_with("ThreadId(") && unnamed.ends_with(')'), "{unnamed}");
+    }
+  }
+
+  #[test]
+  fn uptime_and_delta() {
+    let start = Instant::now().checked_sub(Duration::from_millis(1500)).expect("uptime too short");
+    let options = DefaultFormatOptions::new().timestamp(TimestampFormat::Uptime).delta(true);
+
+    // The delta is per thread, a new thread has no previous record.
+    std::thread::spawn(move || {
+      let record = Record::builder().level(Level::Info).args(format_args!("started")).build();
+      let extensions = Vec::new();
+      let ctx = LogContext::new(UNIX_EPOCH, 0, start, None, &extensions);
+      let mut msg = String::new();
+      options.format_with_context(&ctx, &record, &mut msg).expect("format");
+      assert!(msg.starts_with("[I] - +1.5"), "{msg}");
+      assert!(msg.contains("s (+0.000000s) - ThreadId("), "{msg}");
+
+      let msg = options.format(UNIX_EPOCH, &record).expect("format");
+      assert!(msg.starts_with("[I] - 01 Jan 1970 00:00:00.000 UTC - ThreadId("), "{msg}");
+    })
+    .join()
+    .expect("thread panicked");
+  }
+}
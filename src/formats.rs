@@ -0,0 +1,383 @@
+use std::fmt::{self, Write};
+
+use log::Record;
+
+use crate::format::write_elapsed;
+use crate::{context, DefaultFormatOptions, Error, LogContext};
+
+/// Timestamps of the json, logfmt and pattern formats, `2025-02-17T22:27:20.956Z`.
+const RFC3339: DefaultFormatOptions = DefaultFormatOptions::RFC3339;
+
+/// Writes everything it is given to the buffer, escaped as the content of a JSON string.
+struct JsonEscape<'a>(&'a mut String);
+
+impl Write for JsonEscape<'_> {
+  fn write_str(&mut self, s: &str) -> fmt::Result {
+    for c in s.chars() {
+      match c {
+        '"' => self.0.push_str("\\\""),
+        '\\' => self.0.push_str("\\\\"),
+        '\n' => self.0.push_str("\\n"),
+        '\r' => self.0.push_str("\\r"),
+        '\t' => self.0.push_str("\\t"),
+        c if c.is_control() => write!(self.0, "\\u{:04x}", u32::from(c))?,
+        c => self.0.push(c),
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Writes everything it is given to the buffer, escaped as the content of a quoted logfmt value.
+struct LogfmtEscape<'a>(&'a mut String);
+
+impl Write for LogfmtEscape<'_> {
+  fn write_str(&mut self, s: &str) -> fmt::Result {
+    for c in s.chars() {
+      match c {
+        '"' => self.0.push_str("\\\""),
+        '\\' => self.0.push_str("\\\\"),
+        '\n' => self.0.push_str("\\n"),
+        c => self.0.push(c),
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Writes the thread name, or the id if the thread has no name.
+fn write_thread(buf: &mut impl Write, ctx: &LogContext<'_>) -> fmt::Result {
+  match ctx.thread().name() {
+    Some(name) => buf.write_str(name),
+    None => write!(buf, "{:?}", ctx.thread().id()),
+  }
+}
+
+/// Writes the kernel thread id, or the thread id where it is not supported.
+fn write_tid(buf: &mut impl Write, ctx: &LogContext<'_>) -> fmt::Result {
+  match ctx.os_thread_id() {
+    Some(tid) => write!(buf, "{tid}"),
+    None => write!(buf, "{:?}", ctx.thread().id()),
+  }
+}
+
+/// Calls `func` for the global fields and then the context of the current thread.
+fn for_each_field(ctx: &LogContext<'_>, mut func: impl FnMut(&str, &str)) {
+  ctx.for_each_global_field(&mut func);
+  context::for_each(func);
+}
+
+/// One JSON object per line:
+/// `{"ts":"2025-02-17T22:27:20.956Z","level":"INFO","seq":42,"thread":"main","tid":4711,"target":"app","msg":"started","request_id":"abc"}`.
+/// `tid` is the kernel thread id and only present on Linux.
+/// Global fields and the context are added as top level string members.
+pub fn json(ctx: &LogContext<'_>, record: &Record<'_>, buf: &mut String) -> fmt::Result {
+  buf.push_str("{\"ts\":\"");
+  RFC3339.write_time(ctx.now(), buf)?;
+  write!(buf, "\",\"level\":\"{}\",\"seq\":{},\"thread\":\"", record.level(), ctx.sequence())?;
+  write_thread(&mut JsonEscape(buf), ctx)?;
+  buf.push('"');
+  if let Some(tid) = ctx.os_thread_id() {
+    write!(buf, ",\"tid\":{tid}")?;
+  }
+  buf.push_str(",\"target\":\"");
+  JsonEscape(buf).write_str(record.target())?;
+  buf.push_str("\",\"msg\":\"");
+  write!(JsonEscape(buf), "{}", record.args())?;
+  buf.push('"');
+
+  for_each_field(ctx, |key, value| {
+    buf.push_str(",\"");
+    _ = JsonEscape(buf).write_str(key);
+    buf.push_str("\":\"");
+    _ = JsonEscape(buf).write_str(value);
+    buf.push('"');
+  });
+
+  buf.push_str("}\n");
+  Ok(())
+}
+
+/// Writes a logfmt value, quoted if required.
+fn logfmt_value(buf: &mut String, value: &str) {
+  if !value.is_empty()
+    && !value.contains(|c: char| c == ' ' || c == '=' || c == '"' || c.is_control())
+  {
+    buf.push_str(value);
+    return;
+  }
+
+  buf.push('"');
+  _ = LogfmtEscape(buf).write_str(value);
+  buf.push('"');
+}
+
+/// Writes a logfmt key, characters that would end the key are replaced with `_`.
+fn logfmt_key(buf: &mut String, key: &str) {
+  if key.is_empty() {
+    buf.push('_');
+  }
+  for c in key.chars() {
+    buf.push(if c == ' ' || c == '=' || c == '"' || c.is_control() { '_' } else { c });
+  }
+}
+
+/// One logfmt line: `ts=2025-02-17T22:27:20.956Z level=info seq=42 thread=main tid=4711 target=app msg="started" request_id=abc`.
+/// `tid` is the kernel thread id and only present on Linux.
+/// Global fields and the context are appended as additional keys, spaces, `=` and quotes in keys are replaced with `_`.
+pub fn logfmt(ctx: &LogContext<'_>, record: &Record<'_>, buf: &mut String) -> fmt::Result {
+  buf.push_str("ts=");
+  RFC3339.write_time(ctx.now(), buf)?;
+  let level = record.level().as_str();
+  buf.push_str(" level=");
+  for c in level.chars() {
+    buf.push(c.to_ascii_lowercase());
+  }
+  write!(buf, " seq={} thread=", ctx.sequence())?;
+  match ctx.thread().name() {
+    Some(name) => logfmt_value(buf, name),
+    None => write!(buf, "{:?}", ctx.thread().id())?,
+  }
+  if let Some(tid) = ctx.os_thread_id() {
+    write!(buf, " tid={tid}")?;
+  }
+  buf.push_str(" target=");
+  logfmt_value(buf, record.target());
+  buf.push_str(" msg=\"");
+  write!(LogfmtEscape(buf), "{}", record.args())?;
+  buf.push('"');
+
+  for_each_field(ctx, |key, value| {
+    buf.push(' ');
+    logfmt_key(buf, key);
+    buf.push('=');
+    logfmt_value(buf, value);
+  });
+
+  buf.push('\n');
+  Ok(())
+}
+
+/// A placeholder of a pattern.
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Token {
+  /// Text between placeholders
+  Literal(String),
+  /// `{level}`, `INFO`
+  Level,
+  /// `{timestamp}`, RFC 3339 in UTC
+  Timestamp,
+  /// `{uptime}`, `+1.234567s` since `Builder::init`
+  Uptime,
+  /// `{delta}`, `+0.000123s` since the previous record of the thread
+  Delta,
+  /// `{seq}`, the sequence number
+  Sequence,
+  /// `{thread}`, the thread name or id
+  Thread,
+  /// `{tid}`, the kernel thread id, the thread id where it is not supported
+  Tid,
+  /// `{thread_tid}`, the thread name or id followed by the kernel thread id, `main(4711)`
+  ThreadTid,
+  /// `{target}`
+  Target,
+  /// `{file}`
+  File,
+  /// `{line}`
+  Line,
+  /// `{message}`
+  Message,
+  /// `{fields}`, global fields and context as `key=value` separated by spaces
+  Fields,
+}
+
+/// A format described by a pattern such as `{timestamp} {level} {target} - {message}`, a newline is appended.
+/// `{{` and `}}` produce literal braces.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Pattern(Vec<Token>);
+
+impl Pattern {
+  /// Parses the pattern.
+  /// # Errors
+  /// If a placeholder is unknown or not closed, the line of the error is `line`.
+  pub fn parse(pattern: &str, line: Option<usize>) -> Result<Self, Error> {
+    let error = |message: String| Error::Config { line, message };
+    let mut tokens = Vec::new();
+    let mut literal = String::new();
+    let mut chars = pattern.chars();
+
+    while let Some(c) = chars.next() {
+      match c {
+        '{' if chars.as_str().starts_with('{') => {
+          chars.next();
+          literal.push('{');
+        }
+        '}' if chars.as_str().starts_with('}') => {
+          chars.next();
+          literal.push('}');
+        }
+        '{' => {
+          let rest = chars.as_str();
+          let Some(end) = rest.find('}') else {
+            return Err(error(format!("unclosed placeholder in pattern `{pattern}`")));
+          };
+          let name = rest.get(..end).unwrap_or_default();
+          let token = match name {
+            "level" => Token::Level,
+            "timestamp" => Token::Timestamp,
+            "uptime" => Token::Uptime,
+            "delta" => Token::Delta,
+            "seq" => Token::Sequence,
+            "thread" => Token::Thread,
+            "tid" => Token::Tid,
+            "thread_tid" => Token::ThreadTid,
+            "target" => Token::Target,
+            "file" => Token::File,
+            "line" => Token::Line,
+            "message" => Token::Message,
+            "fields" => Token::Fields,
+            _ => return Err(error(format!("unknown placeholder `{{{name}}}` in pattern"))),
+          };
+          if !literal.is_empty() {
+            tokens.push(Token::Literal(std::mem::take(&mut literal)));
+          }
+          tokens.push(token);
+          chars = rest.get(end + 1..).unwrap_or_default().chars();
+        }
+        c => literal.push(c),
+      }
+    }
+
+    if !literal.is_empty() {
+      tokens.push(Token::Literal(literal));
+    }
+    Ok(Self(tokens))
+  }
+
+  /// Formats the record according to the pattern.
+  pub fn format_into(
+    &self,
+    ctx: &LogContext<'_>,
+    record: &Record<'_>,
+    buf: &mut String,
+  ) -> fmt::Result {
+    for token in &self.0 {
+      match token {
+        Token::Literal(literal) => buf.push_str(literal),
+        Token::Level => buf.push_str(record.level().as_str()),
+        Token::Timestamp => RFC3339.write_time(ctx.now(), buf)?,
+        Token::Uptime => write_elapsed(buf, ctx.uptime())?,
+        Token::Delta => write_elapsed(buf, ctx.delta())?,
+        Token::Sequence => write!(buf, "{}", ctx.sequence())?,
+        Token::Thread => write_thread(buf, ctx)?,
+        Token::Tid => write_tid(buf, ctx)?,
+        Token::ThreadTid => {
+          write_thread(buf, ctx)?;
+          if let Some(tid) = ctx.os_thread_id() {
+            write!(buf, "({tid})")?;
+          }
+        }
+        Token::Target => buf.push_str(record.target()),
+        Token::File => buf.push_str(record.file().unwrap_or("<unknown>")),
+        Token::Line => write!(buf, "{}", record.line().unwrap_or(0))?,
+        Token::Message => write!(buf, "{}", record.args())?,
+        Token::Fields => {
+          let mut separator = "";
+          for_each_field(ctx, |key, value| {
+            buf.push_str(separator);
+            buf.push_str(key);
+            buf.push('=');
+            buf.push_str(value);
+            separator = " ";
+          });
+        }
+      }
+    }
+
+    buf.push('\n');
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::time::{Instant, UNIX_EPOCH};
+
+  use log::{Level, Record};
+
+  use crate::formats::{json, logfmt, Pattern};
+  use crate::LogContext;
+
+  /// The kernel thread id of the current thread.
+  #[cfg(target_os = "linux")]
+  fn tid() -> u64 {
+    crate::util::os_thread_id().expect("tid")
+  }
+
+  fn format(
+    func: impl Fn(&LogContext<'_>, &Record<'_>, &mut String) -> std::fmt::Result,
+  ) -> String {
+    let extensions = Vec::new();
+    let ctx = LogContext::new(UNIX_EPOCH, 7, Instant::now(), None, &extensions);
+    let record = Record::builder()
+      .level(Level::Warn)
+      .target("app::db")
+      .line(Some(3))
+      .args(format_args!("say \"hi\"\n"))
+      .build();
+
+    let mut buf = String::new();
+    crate::context::scope(&[("user", "a b")], || func(&ctx, &record, &mut buf)).expect("format");
+    buf
+  }
+
+  #[test]
+  fn structured() {
+    let msg = format(json);
+    assert!(
+      msg.starts_with(
+        "{\"ts\":\"1970-01-01T00:00:00.000Z\",\"level\":\"WARN\",\"seq\":7,\"thread\":\""
+      ),
+      "{msg}"
+    );
+    assert!(
+      msg.ends_with("\"target\":\"app::db\",\"msg\":\"say \\\"hi\\\"\\n\",\"user\":\"a b\"}\n"),
+      "{msg}"
+    );
+    #[cfg(target_os = "linux")]
+    assert!(msg.contains(&format!("\",\"tid\":{},\"target\":", tid())), "{msg}");
+
+    let msg = format(logfmt);
+    assert!(msg.starts_with("ts=1970-01-01T00:00:00.000Z level=warn seq=7 thread="), "{msg}");
+    assert!(msg.ends_with(" target=app::db msg=\"say \\\"hi\\\"\\n\" user=\"a b\"\n"), "{msg}");
+    #[cfg(target_os = "linux")]
+    assert!(msg.contains(&format!(" tid={} target=", tid())), "{msg}");
+
+    let msg =
+      crate::context::scope(&[("user id", "7"), ("a=b", "c"), ("", "d")], || format(logfmt));
+    assert!(msg.ends_with(" user_id=7 a_b=c _=d user=\"a b\"\n"), "{msg}");
+  }
+
+  #[test]
+  fn pattern() {
+    let pattern = Pattern::parse("{{{seq}}} {level} {target}:{line} {fields} - {message}", None)
+      .expect("parse");
+    let msg = format(|ctx, record, buf| pattern.format_into(ctx, record, buf));
+    assert_eq!(msg, "{7} WARN app::db:3 user=a b - say \"hi\"\n\n");
+
+    let pattern = Pattern::parse("{tid} [{thread_tid}]", None).expect("parse");
+    let msg = std::thread::Builder::new()
+      .name("worker".to_string())
+      .spawn(move || format(|ctx, record, buf| pattern.format_into(ctx, record, buf)))
+      .expect("spawn")
+      .join()
+      .expect("join");
+    #[cfg(target_os = "linux")]
+    assert_eq!(msg, format!("{0} [worker({0})]\n", msg.split(' ').next().unwrap_or_default()));
+    #[cfg(not(target_os = "linux"))]
+    assert!(msg.ends_with(" [worker]\n"), "{msg}");
+
+    assert!(Pattern::parse("{level", None).is_err());
+    assert!(Pattern::parse("{lvl}", None).is_err());
+  }
+}
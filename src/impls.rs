@@ -1,12 +1,28 @@
+use std::fmt;
 use std::fs::{File, OpenOptions};
-use std::io::{BufWriter, Write};
+use std::io::{self, BufWriter, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{mpsc, Arc, Mutex};
 
-use crate::{Appender, IntoAppender};
+use log::Record;
+
+use crate::{Appender, IntoAppender, LogContext};
 
 /// Appender for `BufWriter`
-struct AppenderWriter<X: Write + Send>(Mutex<BufWriter<X>>);
+struct AppenderWriter<X: Write + Send> {
+  /// The writer
+  writer: Mutex<BufWriter<X>>,
+  /// Number of messages that could not be written
+  dropped: AtomicU64,
+}
+
+impl<X: Write + Send> AppenderWriter<X> {
+  /// Wraps the writer.
+  const fn new(writer: BufWriter<X>) -> Self {
+    Self { writer: Mutex::new(writer), dropped: AtomicU64::new(0) }
+  }
+}
 
 impl<T, Y> IntoAppender<Y> for T
 where
@@ -26,49 +42,286 @@ where
   }
 }
 
-impl<X: Write + Send> Appender<String> for AppenderWriter<X> {
-  fn append_log_message(&self, message: &String) {
-    if let Ok(mut guard) = self.0.lock() {
-      // We ignore errors
-      _ = guard.write_all(message.as_bytes());
+impl<X: Write + Send, T: AsRef<[u8]>> Appender<T> for AppenderWriter<X> {
+  fn append_log_message(&self, message: &T) {
+    let written = self.writer.lock().is_ok_and(|mut guard| {
+      // Errors are only counted
+      guard.write_all(message.as_ref()).and_then(|()| guard.flush()).is_ok()
+    });
+    if !written {
+      self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  fn flush(&self) {
+    if let Ok(mut guard) = self.writer.lock() {
       _ = guard.flush();
     }
   }
+
+  fn dropped(&self) -> u64 {
+    self.dropped.load(Ordering::Relaxed)
+  }
+}
+
+/// Opens the file for appending, creating it if required.
+fn open_append(path: &Path) -> File {
+  match OpenOptions::new().append(true).create(true).open(path) {
+    Ok(file) => file,
+    Err(err) => {
+      panic!("Failed to open or create log file {} reason: {}", path.to_string_lossy(), err)
+    }
+  }
 }
 
 impl<X: Write + Send + 'static> IntoAppender<String> for BufWriter<X> {
   fn into_appender(self) -> Arc<dyn Appender<String>> {
-    Arc::new(AppenderWriter(Mutex::new(self)))
+    Arc::new(AppenderWriter::new(self))
   }
 }
 
 impl IntoAppender<String> for File {
   fn into_appender(self) -> Arc<dyn Appender<String>> {
-    Arc::new(AppenderWriter(Mutex::new(BufWriter::new(self))))
+    Arc::new(AppenderWriter::new(BufWriter::new(self)))
   }
 }
 
 impl IntoAppender<String> for &Path {
   fn into_appender(self) -> Arc<dyn Appender<String>> {
-    match OpenOptions::new().append(true).create(true).open(self) {
-      Ok(file) => file.into_appender(),
-      Err(err) => {
-        panic!("Failed to open or create log file {} reason: {}", self.to_string_lossy(), err)
-      }
+    open_append(self).into_appender()
+  }
+}
+
+impl<X: Write + Send + 'static> IntoAppender<Vec<u8>> for BufWriter<X> {
+  fn into_appender(self) -> Arc<dyn Appender<Vec<u8>>> {
+    Arc::new(AppenderWriter::new(self))
+  }
+}
+
+impl IntoAppender<Vec<u8>> for File {
+  fn into_appender(self) -> Arc<dyn Appender<Vec<u8>>> {
+    Arc::new(AppenderWriter::new(BufWriter::new(self)))
+  }
+}
+
+impl IntoAppender<Vec<u8>> for &Path {
+  fn into_appender(self) -> Arc<dyn Appender<Vec<u8>>> {
+    open_append(self).into_appender()
+  }
+}
+
+/// Adapts an appender for bytes (`Appender<Vec<u8>>`) to `String` messages.
+///
+/// Every message is copied into a `Vec<u8>`, so prefer registering byte appenders
+/// such as `File` or `BufWriter` directly with a format that produces `Vec<u8>`.
+///
+///```rust
+/// use log::LevelFilter;
+/// use trivial_log::{Appender, BytesAdapter};
+///
+/// struct Socket;
+/// impl Appender<Vec<u8>> for Socket {
+///   fn append_log_message(&self, message: &Vec<u8>) {
+///     // send the bytes
+///   }
+/// }
+///
+/// trivial_log::builder()
+///   .default_format(|builder| builder.appender_filter(LevelFilter::Info, BytesAdapter::new(Socket)))
+///   .init()
+///   .unwrap();
+/// trivial_log::free();
+///```
+pub struct BytesAdapter(Arc<dyn Appender<Vec<u8>>>);
+
+impl BytesAdapter {
+  /// Wraps the appender.
+  pub fn new(inner: impl IntoAppender<Vec<u8>>) -> Self {
+    Self(inner.into_appender())
+  }
+}
+
+impl Appender<String> for BytesAdapter {
+  fn append_log_message(&self, message: &String) {
+    self.0.append_log_message(&message.clone().into_bytes());
+  }
+
+  fn append_log_record(&self, record: &Record<'_>, message: &String) {
+    self.0.append_log_record(record, &message.clone().into_bytes());
+  }
+
+  fn append_log_context(&self, ctx: &LogContext<'_>, record: &Record<'_>, message: &String) {
+    self.0.append_log_context(ctx, record, &message.clone().into_bytes());
+  }
+
+  fn flush(&self) {
+    self.0.flush();
+  }
+
+  fn dump(&self, out: &mut dyn io::Write) {
+    self.0.dump(out);
+  }
+
+  fn dropped(&self) -> u64 {
+    self.0.dropped()
+  }
+}
+
+/// Handle to the number of messages a registered appender dropped, see `AppenderBuilder::appender_counted`.
+///
+/// The handle keeps the appender alive, a file appender stays open until the handle is dropped.
+#[derive(Clone)]
+pub struct DropCounter(Arc<dyn Fn() -> u64 + Send + Sync>);
+
+impl DropCounter {
+  /// Reads the counter of the appender.
+  pub(crate) fn new<T: 'static>(appender: Arc<dyn Appender<T>>) -> Self {
+    Self(Arc::new(move || appender.dropped()))
+  }
+
+  /// Number of messages the appender discarded or failed to write, see `Appender::dropped`.
+  #[must_use]
+  pub fn get(&self) -> u64 {
+    (self.0)()
+  }
+}
+
+impl fmt::Debug for DropCounter {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("DropCounter").field(&self.get()).finish()
+  }
+}
+
+/// Appender for the sending half of a channel (`mpsc::Sender` or `mpsc::SyncSender`).
+///
+/// Senders of `String` and `Vec<u8>` can be registered directly, this wrapper is needed for other message types.
+/// Once the receiver is gone every message is counted in `Appender::dropped`.
+///
+///```rust
+/// use log::LevelFilter;
+/// use std::sync::mpsc;
+/// use trivial_log::ChannelAppender;
+///
+/// let (sender, receiver) = mpsc::channel::<(log::Level, String)>();
+/// trivial_log::builder()
+///   .format(
+///     |_, record| Some((record.level(), record.args().to_string())),
+///     |builder| builder.appender_filter(LevelFilter::Info, ChannelAppender::new(sender)),
+///   )
+///   .init()
+///   .unwrap();
+/// log::info!("hello");
+/// assert_eq!(receiver.recv().unwrap(), (log::Level::Info, "hello".to_string()));
+/// trivial_log::free();
+///```
+pub struct ChannelAppender<S> {
+  /// The sender
+  sender: S,
+  /// Number of messages that could not be sent because the receiver is gone
+  dropped: AtomicU64,
+}
+
+impl<S> ChannelAppender<S> {
+  /// Wraps the sender.
+  pub const fn new(sender: S) -> Self {
+    Self { sender, dropped: AtomicU64::new(0) }
+  }
+
+  /// Counts the message as dropped if it could not be sent.
+  fn count(&self, sent: bool) {
+    if !sent {
+      self.dropped.fetch_add(1, Ordering::Relaxed);
     }
   }
 }
 
-impl<X: Send + Clone> Appender<X> for mpsc::Sender<X> {
+impl<X: Send + Clone> Appender<X> for ChannelAppender<mpsc::Sender<X>> {
   fn append_log_message(&self, message: &X) {
-    // We don't care if the receiving end is dead.
-    _ = self.send(message.clone());
+    self.count(self.sender.send(message.clone()).is_ok());
+  }
+
+  fn dropped(&self) -> u64 {
+    self.dropped.load(Ordering::Relaxed)
   }
 }
 
-impl<X: Send + Clone> Appender<X> for mpsc::SyncSender<X> {
+impl<X: Send + Clone> Appender<X> for ChannelAppender<mpsc::SyncSender<X>> {
   fn append_log_message(&self, message: &X) {
-    // We don't care if the receiving end is dead.
-    _ = self.send(message.clone());
+    self.count(self.sender.send(message.clone()).is_ok());
+  }
+
+  fn dropped(&self) -> u64 {
+    self.dropped.load(Ordering::Relaxed)
+  }
+}
+
+impl IntoAppender<String> for mpsc::Sender<String> {
+  fn into_appender(self) -> Arc<dyn Appender<String>> {
+    Arc::new(ChannelAppender::new(self))
+  }
+}
+
+impl IntoAppender<String> for mpsc::SyncSender<String> {
+  fn into_appender(self) -> Arc<dyn Appender<String>> {
+    Arc::new(ChannelAppender::new(self))
+  }
+}
+
+impl IntoAppender<Vec<u8>> for mpsc::Sender<Vec<u8>> {
+  fn into_appender(self) -> Arc<dyn Appender<Vec<u8>>> {
+    Arc::new(ChannelAppender::new(self))
+  }
+}
+
+impl IntoAppender<Vec<u8>> for mpsc::SyncSender<Vec<u8>> {
+  fn into_appender(self) -> Arc<dyn Appender<Vec<u8>>> {
+    Arc::new(ChannelAppender::new(self))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::fs;
+  use std::path::Path;
+  use std::sync::{mpsc, Arc, Mutex};
+
+  use crate::{Appender, BytesAdapter, ChannelAppender, DropCounter, IntoAppender};
+
+  #[test]
+  fn bytes() {
+    let path = std::env::temp_dir().join(format!("trivial_log_bytes_{}.log", std::process::id()));
+    _ = fs::remove_file(&path);
+
+    let appender: Arc<dyn Appender<Vec<u8>>> = path.as_path().into_appender();
+    appender.append_log_message(&vec![0, 159, 146, 150]);
+    let appender: Arc<dyn Appender<String>> = Path::new(&path).into_appender();
+    appender.append_log_message(&"text\n".to_string());
+    assert_eq!(fs::read(&path).expect("read"), b"\x00\x9f\x92\x96text\n");
+    _ = fs::remove_file(&path);
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&received);
+    let adapter = BytesAdapter::new(move |msg: &Vec<u8>| {
+      sink.lock().expect("lock").extend_from_slice(msg);
+    });
+    adapter.append_log_message(&"hello".to_string());
+    assert_eq!(received.lock().expect("lock").as_slice(), b"hello");
+  }
+
+  #[test]
+  fn channel_drops() {
+    let (sender, receiver) = mpsc::channel::<String>();
+    let appender = sender.into_appender();
+    let counter = DropCounter::new(Arc::clone(&appender));
+    appender.append_log_message(&"received".to_string());
+    drop(receiver);
+    appender.append_log_message(&"lost".to_string());
+    assert_eq!(counter.get(), 1);
+
+    let (sender, receiver) = mpsc::sync_channel::<u32>(1);
+    let appender = ChannelAppender::new(sender);
+    drop(receiver);
+    appender.append_log_message(&7);
+    assert_eq!(appender.dropped(), 1);
   }
 }
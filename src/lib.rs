@@ -11,8 +11,12 @@
 //!
 
 use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::any::Any;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock, RwLockReadGuard, TryLockError};
-use std::time::SystemTime;
+use std::time::{Instant, SystemTime};
 
 /// error types
 mod error;
@@ -23,64 +27,132 @@ mod impls;
 /// Utility functions
 mod util;
 
+/// Options for the default format.
+mod format;
+
+/// Thread local buffer reused by `Builder::format_into`.
+mod buffer;
+
+/// Dependency free conversion of unix timestamps to calendar dates.
+mod calendar;
+
+/// Appender wrapper that limits the rate of messages per target or callsite.
+mod rate_limit;
+
+/// Appender wrapper that collapses consecutive identical messages.
+mod dedup;
+
+/// Appender wrapper that forwards only a sample of the records.
+mod sampled;
+
+/// Appender that keeps the last N messages in memory.
+mod ring_buffer;
+
+/// Key-value pairs attached to all records logged by the current thread, for example request or user ids.
+pub mod context;
+
+/// Information passed to format fns besides the record.
+mod log_context;
+
+/// Panic hook that logs panics and dumps buffered messages.
+mod panic;
+
+/// Options for logging to stdout/stderr.
+mod stdio;
+
+/// Appenders that write to stdout/stderr without panicking.
+mod std_appender;
+
+/// Appender that rotates files by size.
+mod rolling;
+
+/// Appender that names files after the date of their messages.
+mod dated;
+
+/// Appender that sends messages to the local syslog daemon.
+#[cfg(unix)]
+mod syslog;
+
+/// The json, logfmt and pattern formats of the config.
+mod formats;
+
+/// Building the logger from a configuration file.
+pub mod config;
+
+/// Reloading the configuration file when it changes.
+mod watch;
+
+/// Runs tests that need a process of their own.
+#[cfg(test)]
+mod child;
+
+pub use dated::DatedFileAppender;
+pub use dedup::Dedup;
 pub use error::Error;
+pub use format::{DefaultFormatOptions, ThreadFormat, TimeZone, TimestampFormat};
+pub use impls::{BytesAdapter, ChannelAppender, DropCounter};
+pub use log_context::LogContext;
+pub use panic::install_panic_hook;
+pub use rate_limit::{RateLimitKey, RateLimited};
+pub use ring_buffer::RingBufferAppender;
+pub use rolling::RollingFileAppender;
+pub use sampled::Sampled;
+pub use std_appender::{StderrAppender, StdoutAppender};
+pub use stdio::{ColorChoice, StdOptions, StdSplit, StdStream};
+#[cfg(unix)]
+pub use syslog::SyslogAppender;
 
 /// Initializes `log` to forward all log to stdout using the default format
+///
+/// Accepts either a `LevelFilter` or `StdOptions` to customize the default format.
+/// The level prefix is colored if stdout is a terminal, see `StdOptions::color`.
 /// # Errors
 /// Only if there is already another log implementation initialized
-pub fn init_stdout(level: LevelFilter) -> Result<(), Error> {
-  builder()
-    .default_format(|builder| builder.appender_filter(level, |msg: &String| print!("{msg}")))
-    .init()
+pub fn init_stdout(options: impl Into<StdOptions>) -> Result<(), Error> {
+  init_std(options.into().split(StdSplit::all(StdStream::Stdout)))
 }
 
 /// Initializes `log` to forward all log to stderr using the default format
+///
+/// Accepts either a `LevelFilter` or `StdOptions` to customize the default format.
+/// The level prefix is colored if stderr is a terminal, see `StdOptions::color`.
 /// # Errors
 /// Only if there is already another log implementation initialized
-pub fn init_stderr(level: LevelFilter) -> Result<(), Error> {
-  builder()
-    .default_format(|builder| builder.appender_filter(level, |msg: &String| eprint!("{msg}")))
-    .init()
+pub fn init_stderr(options: impl Into<StdOptions>) -> Result<(), Error> {
+  init_std(options.into().split(StdSplit::all(StdStream::Stderr)))
 }
 
 /// Initializes `log` to forward all warn and below to stdout and all error to stderr
+///
+/// Accepts either a `LevelFilter` or `StdOptions` to customize the default format.
+/// `StdOptions::split` changes which levels go to stdout and which to stderr.
+/// The level prefix is colored if the stream is a terminal, see `StdOptions::color`.
 /// # Errors
 /// Only if there is already another log implementation initialized
-pub fn init_std(level: LevelFilter) -> Result<(), Error> {
-  match level {
-    LevelFilter::Off => builder().init(),
-    LevelFilter::Error => builder()
-      .default_format(|builder| builder.appender(Level::Error, |msg: &String| eprint!("{msg}")))
-      .init(),
-    LevelFilter::Warn => builder()
-      .default_format(|builder| {
-        builder
-          .appender(Level::Warn, |msg: &String| eprint!("{msg}"))
-          .appender(Level::Error, |msg: &String| eprint!("{msg}"))
-      })
-      .init(),
-    LevelFilter::Info => builder()
-      .default_format(|builder| {
-        builder
-          .appender_range(Level::Info, Level::Warn, |msg: &String| eprint!("{msg}"))
-          .appender(Level::Error, |msg: &String| eprint!("{msg}"))
-      })
-      .init(),
-    LevelFilter::Debug => builder()
-      .default_format(|builder| {
-        builder
-          .appender_range(Level::Debug, Level::Warn, |msg: &String| eprint!("{msg}"))
-          .appender(Level::Error, |msg: &String| eprint!("{msg}"))
-      })
-      .init(),
-    LevelFilter::Trace => builder()
-      .default_format(|fmt| {
-        fmt
-          .appender_range(Level::Trace, Level::Warn, |msg: &String| eprint!("{msg}"))
-          .appender(Level::Error, |msg: &String| eprint!("{msg}"))
-      })
-      .init(),
-  }
+pub fn init_std(options: impl Into<StdOptions>) -> Result<(), Error> {
+  let options = options.into();
+  let levels = |stream: StdStream| {
+    Level::iter()
+      .filter(move |level| *level <= options.level && options.split.stream(*level) == stream)
+  };
+
+  // One appender per stream, shared by all of its levels.
+  let stdout = StdoutAppender::new().into_appender();
+  let stderr = StderrAppender::new().into_appender();
+  builder()
+    .default_format_with(options.format_for(&io::stdout()), |mut builder| {
+      for level in levels(StdStream::Stdout) {
+        builder = builder.push(level, level, &stdout);
+      }
+      builder
+    })
+    .default_format_with(options.format_for(&io::stderr()), |mut builder| {
+      for level in levels(StdStream::Stderr) {
+        builder = builder.push(level, level, &stderr);
+      }
+      builder
+    })
+    .init()
 }
 
 #[must_use]
@@ -91,7 +163,7 @@ pub fn builder() -> Builder {
 /// Builder for adding appenders to a format `Fn`.
 pub struct AppenderBuilder<T> {
   /// The format fn
-  format: Box<FormatFn<T>>,
+  format: Box<dyn Formatter<T>>,
   /// The appenders grouped by level.
   appender: [Vec<Arc<dyn Appender<T>>>; 5],
 }
@@ -104,22 +176,71 @@ impl<T> AppenderBuilder<T> {
 
   #[must_use]
   pub fn appender_filter(self, filter: LevelFilter, appender: impl IntoAppender<T>) -> Self {
+    if filter == LevelFilter::Off {
+      return self;
+    }
+    self.push_filter(filter, &appender.into_appender())
+  }
+
+  #[must_use]
+  pub fn appender_range(self, from: Level, to: Level, appender: impl IntoAppender<T>) -> Self {
+    self.push(from, to, &appender.into_appender())
+  }
+
+  /// Registers the appender like `appender_filter` and returns a handle to the number of messages
+  /// it dropped (`Appender::dropped`), which stays readable after `trivial_log::free()`.
+  ///
+  ///```rust
+  /// use log::LevelFilter;
+  /// use std::sync::mpsc;
+  ///
+  /// let (sender, receiver) = mpsc::channel::<String>();
+  /// let mut counter = None;
+  /// trivial_log::builder()
+  ///   .default_format(|builder| {
+  ///     let (builder, dropped) = builder.appender_counted(LevelFilter::Info, sender);
+  ///     counter = Some(dropped);
+  ///     builder
+  ///   })
+  ///   .init()
+  ///   .unwrap();
+  ///
+  /// drop(receiver);
+  /// log::info!("nobody receives this");
+  /// assert_eq!(counter.unwrap().get(), 1);
+  /// trivial_log::free();
+  ///```
+  #[must_use]
+  pub fn appender_counted(
+    self,
+    filter: LevelFilter,
+    appender: impl IntoAppender<T>,
+  ) -> (Self, DropCounter)
+  where
+    T: 'static,
+  {
+    let appender = appender.into_appender();
+    let counter = DropCounter::new(Arc::clone(&appender));
+    (self.push_filter(filter, &appender), counter)
+  }
+
+  /// Registers the appender for the levels of the filter.
+  fn push_filter(self, filter: LevelFilter, appender: &Arc<dyn Appender<T>>) -> Self {
     match filter {
       LevelFilter::Off => self,
-      LevelFilter::Error => self.appender(Level::Error, appender),
-      LevelFilter::Warn => self.appender_range(Level::Warn, Level::Error, appender),
-      LevelFilter::Info => self.appender_range(Level::Info, Level::Error, appender),
-      LevelFilter::Debug => self.appender_range(Level::Debug, Level::Error, appender),
-      LevelFilter::Trace => self.appender_range(Level::Trace, Level::Error, appender),
+      LevelFilter::Error => self.push(Level::Error, Level::Error, appender),
+      LevelFilter::Warn => self.push(Level::Warn, Level::Error, appender),
+      LevelFilter::Info => self.push(Level::Info, Level::Error, appender),
+      LevelFilter::Debug => self.push(Level::Debug, Level::Error, appender),
+      LevelFilter::Trace => self.push(Level::Trace, Level::Error, appender),
     }
   }
 
-  #[must_use]
-  pub fn appender_range(mut self, from: Level, to: Level, appender: impl IntoAppender<T>) -> Self {
-    let wrap = appender.into_appender();
+  /// Registers the appender for all levels from `from` to `to`.
+  fn push(mut self, from: Level, to: Level, appender: &Arc<dyn Appender<T>>) -> Self {
     for i in util::get_idx_for_level(from)..=util::get_idx_for_level(to) {
       if let Some(a) = self.appender.get_mut(i) {
-        a.push(Arc::clone(&wrap));
+        a.push(Arc::clone(appender));
       }
     }
 
@@ -133,6 +254,12 @@ impl<T> AppenderBuilder<T> {
 pub struct Builder {
   /// All handlers already built in the builder. init will transform this into a `HandlerCompound`
   handlers: Vec<Box<dyn Handler>>,
+  /// File to dump the buffered messages to if the program panics.
+  dump_on_panic: Option<PathBuf>,
+  /// Key-value pairs attached to every record.
+  global_fields: Vec<(String, String)>,
+  /// Values format fns can read with `LogContext::extension`.
+  extensions: log_context::Extensions,
 }
 
 impl Builder {
@@ -145,7 +272,37 @@ impl Builder {
     self,
     builder: impl FnOnce(AppenderBuilder<String>) -> AppenderBuilder<String>,
   ) -> Self {
-    self.format(util::default_format, builder)
+    self.default_format_with(DefaultFormatOptions::DEFAULT, builder)
+  }
+
+  /// Use the default format with a colored level prefix for some appenders.
+  /// Colors are disabled if the `NO_COLOR` environment variable is set.
+  /// The passed builder argument `FnOnce` can be used to register the appenders.
+  ///
+  /// This does not check whether the appenders write to a terminal,
+  /// use `init_std` or `DefaultFormatOptions::color` for that.
+  #[must_use]
+  pub fn colored_default_format(
+    self,
+    builder: impl FnOnce(AppenderBuilder<String>) -> AppenderBuilder<String>,
+  ) -> Self {
+    self.default_format_with(DefaultFormatOptions::new().color(stdio::color_from_env()), builder)
+  }
+
+  /// Use the default format with the given options for some appenders.
+  /// The passed builder argument `FnOnce` can be used to register the appenders.
+  ///
+  /// Note: It will lead to better performance if all appenders that use the same format are grouped together and registered in the same closure!
+  #[must_use]
+  pub fn default_format_with(
+    self,
+    options: DefaultFormatOptions,
+    builder: impl FnOnce(AppenderBuilder<String>) -> AppenderBuilder<String>,
+  ) -> Self {
+    let format = move |ctx: &LogContext<'_>, record: &Record<'_>, buf: &mut String| {
+      options.format_with_context(ctx, record, buf)
+    };
+    self.handler(Box::new(buffer::FormatInto(format)), builder)
   }
 
   /// Use a provided format for some appenders.
@@ -155,12 +312,79 @@ impl Builder {
   /// Note: It will lead to better performance if all appenders that use the same format are grouped together and registered in the same closure!
   #[must_use]
   pub fn format<Y: 'static>(
-    mut self,
+    self,
     format: impl Fn(SystemTime, &Record<'_>) -> Option<Y> + Send + Sync + 'static,
     functor: impl FnOnce(AppenderBuilder<Y>) -> AppenderBuilder<Y>,
   ) -> Self {
-    let result =
-      functor(AppenderBuilder { format: Box::new(format), appender: [const { Vec::new() }; 5] });
+    self.format_with_context(move |ctx, record| format(ctx.now(), record), functor)
+  }
+
+  /// Use a provided format that receives a `LogContext` for some appenders.
+  /// The context carries the time of the record as well as its sequence number, the uptime of the logger,
+  /// the thread, the global fields and the extensions registered with `Builder::extension`.
+  /// The passed builder argument `FnOnce` can be used to register the appenders which will consume the format struct.
+  ///
+  ///```rust
+  /// use log::LevelFilter;
+  /// trivial_log::builder()
+  ///   .format_with_context(
+  ///     |ctx, record| Some(format!("#{} {:?} {}", ctx.sequence(), ctx.uptime(), record.args())),
+  ///     |builder| builder.appender_filter(LevelFilter::Info, |msg: &String| println!("{msg}")),
+  ///   )
+  ///   .init()
+  ///   .unwrap();
+  /// trivial_log::free();
+  ///```
+  ///
+  /// Note: It will lead to better performance if all appenders that use the same format are grouped together and registered in the same closure!
+  #[must_use]
+  pub fn format_with_context<Y: 'static>(
+    self,
+    format: impl Fn(&LogContext<'_>, &Record<'_>) -> Option<Y> + Send + Sync + 'static,
+    functor: impl FnOnce(AppenderBuilder<Y>) -> AppenderBuilder<Y>,
+  ) -> Self {
+    self.handler(Box::new(format), functor)
+  }
+
+  /// Use a provided format that writes into a reusable buffer for some appenders.
+  /// The passed format argument `Fn` appends the message to the empty `String` it is given,
+  /// the appenders then receive a reference to that `String`.
+  /// The buffer is kept per thread, so formatting does not allocate once the buffer has grown to the size of the messages.
+  /// The passed builder argument `FnOnce` can be used to register the appenders.
+  ///
+  ///```rust
+  /// use std::fmt::Write;
+  /// use log::LevelFilter;
+  /// trivial_log::builder()
+  ///   .format_into(
+  ///     |_, record, buf| writeln!(buf, "{} {}", record.level(), record.args()),
+  ///     |builder| builder.appender_filter(LevelFilter::Info, |msg: &String| print!("{msg}")),
+  ///   )
+  ///   .init()
+  ///   .unwrap();
+  /// trivial_log::free();
+  ///```
+  ///
+  /// Note: It will lead to better performance if all appenders that use the same format are grouped together and registered in the same closure!
+  #[must_use]
+  pub fn format_into(
+    self,
+    format: impl Fn(SystemTime, &Record<'_>, &mut String) -> fmt::Result + Send + Sync + 'static,
+    functor: impl FnOnce(AppenderBuilder<String>) -> AppenderBuilder<String>,
+  ) -> Self {
+    let format = move |ctx: &LogContext<'_>, record: &Record<'_>, buf: &mut String| {
+      format(ctx.now(), record, buf)
+    };
+    self.handler(Box::new(buffer::FormatInto(format)), functor)
+  }
+
+  /// Registers a handler for the formatter unless the functor registered no appenders.
+  fn handler<Y: 'static>(
+    mut self,
+    format: Box<dyn Formatter<Y>>,
+    functor: impl FnOnce(AppenderBuilder<Y>) -> AppenderBuilder<Y>,
+  ) -> Self {
+    let result = functor(AppenderBuilder { format, appender: [const { Vec::new() }; 5] });
     let mut is_empty = true;
     for n in &result.appender {
       if !n.is_empty() {
@@ -177,6 +401,54 @@ impl Builder {
     self
   }
 
+  /// Attaches a key-value pair to every record, for example the service name or version.
+  /// The default format prints global fields after the thread, custom formats read them with `LogContext::for_each_global_field`.
+  /// Setting the same key again replaces the value.
+  #[must_use]
+  pub fn global_field(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+    let key = key.into();
+    let value = value.into();
+    match self.global_fields.iter_mut().find(|(existing, _)| *existing == key) {
+      Some(field) => field.1 = value,
+      None => self.global_fields.push((key, value)),
+    }
+    self
+  }
+
+  /// Makes a value available to format fns through `LogContext::extension`, for example a shared counter or a lookup table.
+  /// Only the first value of each type can be read.
+  #[must_use]
+  pub fn extension<E: Any + Send + Sync>(mut self, value: E) -> Self {
+    self.extensions.push(Arc::new(value));
+    self
+  }
+
+  /// Attaches the hostname of the machine as the global field "hostname".
+  /// On Linux it is read from `/proc/sys/kernel/hostname`, elsewhere from the `HOSTNAME` or `COMPUTERNAME` environment variable.
+  /// Nothing is attached if the hostname is unknown.
+  #[must_use]
+  pub fn hostname_field(self) -> Self {
+    match util::hostname() {
+      Some(hostname) => self.global_field("hostname", hostname),
+      None => self,
+    }
+  }
+
+  /// Attaches the id of the process as the global field "pid".
+  #[must_use]
+  pub fn pid_field(self) -> Self {
+    self.global_field("pid", std::process::id().to_string())
+  }
+
+  /// Installs a panic hook that writes the messages buffered by appenders such as `RingBufferAppender` to the given file.
+  /// The file is created if it does not exist and appended to otherwise.
+  /// The previous panic hook is called afterward and restored by `trivial_log::free()`.
+  #[must_use]
+  pub fn dump_on_panic(mut self, path: impl AsRef<Path>) -> Self {
+    self.dump_on_panic = Some(path.as_ref().to_path_buf());
+    self
+  }
+
   /// Initialize the logging implementation
   /// # Errors
   /// Only if a different logger implementation is in use.
@@ -189,17 +461,23 @@ impl Builder {
       poison.into_inner()
     });
 
-    _ = guard.take();
-    util::set_log_logger_impl_and_level(level)?;
-
-    if level == LevelFilter::Off {
-      return Ok(());
+    let old = guard.take();
+    let result = util::set_log_logger_impl_and_level(level);
+    if result.is_ok() {
+      panic::set_dump_path(self.dump_on_panic);
+      if level != LevelFilter::Off {
+        *guard = Some(HandlerCompound::new(self.handlers, self.global_fields, self.extensions));
+      }
     }
-
-    *guard = Some(HandlerCompound::new(self.handlers));
     drop(guard);
 
-    Ok(())
+    // Dropping appenders can wait for their threads, like the compression of `RollingFileAppender`,
+    // so the old ones are flushed and dropped once the lock is released.
+    if let Some(old) = old {
+      old.flush();
+    }
+
+    result
   }
 }
 
@@ -216,18 +494,43 @@ impl Builder {
 /// Because this function leaves behind a "noop" logger, there will NOT be a problem
 /// if the program calls log! after this fn is called.
 ///
+/// All appenders are flushed before they are dropped.
+/// A panic hook installed by `install_panic_hook` or `Builder::dump_on_panic` is removed and the previous panic hook is restored.
+/// The threads started by `ConfigWatcher::watch` are stopped.
+/// The format buffer and the global fields cached by the calling thread are released.
+///
 /// This function does nothing if called repeatedly.
 /// This function does nothing if the actual logger implementation in use by the log crate is a different one.
 ///
 /// Note: Calling this fn will not allow you to switch to a different logger implementation since that is not a supported use case of the log crate itself.
 pub fn free() {
-  TL.0
-    .write()
-    .unwrap_or_else(|poison| {
-      TL.0.clear_poison();
-      poison.into_inner()
-    })
-    .take();
+  // Stopped first, so they cannot initialize the logger again.
+  watch::stop_all();
+
+  let mut guard = TL.0.write().unwrap_or_else(|poison| {
+    TL.0.clear_poison();
+    poison.into_inner()
+  });
+
+  let old = guard.take();
+  drop(guard);
+
+  // No message can reach the old appenders anymore, they are dropped without holding the lock.
+  if let Some(old) = old {
+    old.flush();
+  }
+
+  panic::uninstall();
+  buffer::release();
+}
+
+/// Returns the number of messages the configured appenders discarded or failed to write, see `Appender::dropped`.
+///
+/// An appender registered with several formats is counted once per format.
+/// Returns 0 if the logger is not initialized or is being configured by another thread.
+#[must_use]
+pub fn dropped() -> u64 {
+  TL.guard().and_then(|guard| guard.as_ref().map(HandlerCompound::dropped)).unwrap_or(0)
 }
 
 /// The static state holder
@@ -235,26 +538,57 @@ static TL: LogImpl = LogImpl(RwLock::new(None));
 
 /// Trait to hide the static dispatch type T from the rest of the implementation behind dynamic dispatch.
 trait Handler: Sync + Send {
-  ///Log the record for the given time.
-  fn log(&self, now: SystemTime, record: &Record<'_>);
+  ///Log the record with the given context.
+  fn log(&self, ctx: &LogContext<'_>, record: &Record<'_>);
 
   /// Does the handler have any appenders for the given level?
   fn is_enabled(&self, level: Level) -> bool;
+
+  /// Flush all appenders of the handler.
+  fn flush(&self) {}
+
+  /// Dump the buffered messages of all appenders of the handler.
+  fn dump(&self, out: &mut dyn io::Write) {
+    _ = out;
+  }
+
+  /// Sum of the dropped messages of all appenders of the handler.
+  fn dropped(&self) -> u64 {
+    0
+  }
 }
 
 /// The format fn
 type FormatFn<T> = dyn Fn(SystemTime, &Record<'_>) -> Option<T> + Send + Sync;
 
+/// Formats a record and passes the message to the appenders.
+trait Formatter<T>: Send + Sync {
+  /// Formats the record and calls `append` with the message unless formatting failed.
+  fn format(&self, ctx: &LogContext<'_>, record: &Record<'_>, append: &mut dyn FnMut(&T));
+}
+
+/// Format fns passed to `Builder::format` and `Builder::format_with_context` return an owned message.
+impl<T, F> Formatter<T> for F
+where
+  F: Fn(&LogContext<'_>, &Record<'_>) -> Option<T> + Send + Sync,
+{
+  fn format(&self, ctx: &LogContext<'_>, record: &Record<'_>, append: &mut dyn FnMut(&T)) {
+    if let Some(message) = self(ctx, record) {
+      append(&message);
+    }
+  }
+}
+
 /// Contains a format fn as well as all appenders associated with the format fn.
 struct HandlerImpl<T> {
   /// The format fn to use to format the `log::Record`
-  format: Box<FormatFn<T>>,
+  format: Box<dyn Formatter<T>>,
   /// The appenders for each level
   appender: [Vec<Arc<dyn Appender<T>>>; 5], //5 is number of levels in log crate
 }
 
 impl<T> Handler for HandlerImpl<T> {
-  fn log(&self, now: SystemTime, record: &Record<'_>) {
+  fn log(&self, ctx: &LogContext<'_>, record: &Record<'_>) {
     let Some(appender_list) = self.appender.get(util::get_idx_for_level(record.level())) else {
       unreachable!();
     };
@@ -263,23 +597,92 @@ impl<T> Handler for HandlerImpl<T> {
       return;
     }
 
-    if let Some(fmt) = (self.format)(now, record) {
+    self.format.format(ctx, record, &mut |fmt| {
       for appender in appender_list {
-        appender.append_log_message(&fmt);
+        appender.append_log_context(ctx, record, fmt);
       }
-    }
+    });
   }
 
   fn is_enabled(&self, level: Level) -> bool {
     let Some(a) = self.appender.get(util::get_idx_for_level(level)) else { return false };
     !a.is_empty()
   }
+
+  fn flush(&self) {
+    self.for_each_appender(|appender| appender.flush());
+  }
+
+  fn dump(&self, out: &mut dyn io::Write) {
+    self.for_each_appender(|appender| appender.dump(out));
+  }
+
+  fn dropped(&self) -> u64 {
+    let mut dropped = 0;
+    self.for_each_appender(|appender| dropped += appender.dropped());
+    dropped
+  }
+}
+
+impl<T> HandlerImpl<T> {
+  /// Calls the fn once for each distinct appender.
+  /// The same appender is usually registered for several levels.
+  fn for_each_appender(&self, mut func: impl FnMut(&dyn Appender<T>)) {
+    let mut seen: Vec<*const ()> = Vec::new();
+    for appender in self.appender.iter().flatten() {
+      let ptr = Arc::as_ptr(appender).cast::<()>();
+      if !seen.contains(&ptr) {
+        seen.push(ptr);
+        func(appender.as_ref());
+      }
+    }
+  }
 }
 
 /// This trait defines an appender which consumes a formatted log message of type T and will "write" it to somewhere like stdout/disk/network/...
 pub trait Appender<T>: Send + Sync {
   /// Called for each formatted log message.
   fn append_log_message(&self, message: &T);
+
+  /// Called for each formatted log message together with the record it was formatted from.
+  /// The default implementation ignores the record and calls `append_log_message`.
+  ///
+  /// Override this if the appender needs to know the level, target or callsite of a message.
+  fn append_log_record(&self, record: &Record<'_>, message: &T) {
+    _ = record;
+    self.append_log_message(message);
+  }
+
+  /// Called for each formatted log message together with the record and the context it was formatted with.
+  /// The default implementation ignores the context and calls `append_log_record`.
+  ///
+  /// Override this if the appender needs the time of the record (`LogContext::now`) or its sequence number.
+  /// Wrappers that forward messages right away should forward this as well.
+  fn append_log_context(&self, ctx: &LogContext<'_>, record: &Record<'_>, message: &T) {
+    _ = ctx;
+    self.append_log_record(record, message);
+  }
+
+  /// Called by `log::logger().flush()`, `trivial_log::free()` and when the logger is reconfigured.
+  /// Appenders that buffer messages should write them out here. The default implementation does nothing.
+  fn flush(&self) {}
+
+  /// Called by the panic hook installed by `Builder::dump_on_panic`.
+  /// Appenders that keep messages in memory (like `RingBufferAppender`) should write them to `out`.
+  /// The default implementation does nothing.
+  fn dump(&self, out: &mut dyn io::Write) {
+    _ = out;
+  }
+
+  /// Number of messages the appender discarded or failed to write since it was created,
+  /// for example because of write errors, rate limiting or sampling.
+  /// Wrappers add the count of the appender they wrap. The default implementation returns 0.
+  ///
+  /// Together with `DefaultFormatOptions::sequence` this tells whether a gap was caused by the appender
+  /// or happened later, for example in the network.
+  fn dropped(&self) -> u64 {
+    0
+  }
 }
 
 /// Custom "Into" trait that produces an Arc<dyn `Appender<T>`.
@@ -295,11 +698,21 @@ struct HandlerCompound {
   handlers: Vec<Box<dyn Handler>>,
   /// contains indices into handlers vec for each level.
   handler_indices: [Vec<usize>; 5],
+  /// Key-value pairs attached to every record, `None` if there are none.
+  global_fields: Option<context::GlobalFields>,
+  /// Values format fns can read with `LogContext::extension`.
+  extensions: log_context::Extensions,
+  /// When the logger was initialized, for `LogContext::uptime`.
+  start: Instant,
 }
 
 impl HandlerCompound {
   /// Pre-calculates which handlers handle which levels and optimizes the Vec for later use.
-  fn new(mut handlers: Vec<Box<dyn Handler>>) -> Self {
+  fn new(
+    mut handlers: Vec<Box<dyn Handler>>,
+    global_fields: Vec<(String, String)>,
+    mut extensions: log_context::Extensions,
+  ) -> Self {
     handlers.shrink_to_fit();
     let mut handler_indices: [Vec<usize>; 5] = [const { Vec::new() }; 5];
 
@@ -323,7 +736,11 @@ impl HandlerCompound {
 
     handler_indices.iter_mut().filter(|idx_vec| !idx_vec.is_empty()).for_each(Vec::shrink_to_fit);
 
-    Self { handlers, handler_indices }
+    let global_fields = (!global_fields.is_empty()).then_some(context::GlobalFields(global_fields));
+
+    extensions.shrink_to_fit();
+
+    Self { handlers, handler_indices, global_fields, extensions, start: Instant::now() }
   }
 
   /// Returns true if at least one handler can handle the level
@@ -331,13 +748,38 @@ impl HandlerCompound {
     self.handler_indices.get(util::get_idx_for_level(level)).is_some_and(Vec::is_empty)
   }
 
+  /// Flushes all handlers
+  fn flush(&self) {
+    for handler in &self.handlers {
+      handler.flush();
+    }
+  }
+
+  /// Dumps the buffered messages of all handlers
+  fn dump(&self, out: &mut dyn io::Write) {
+    for handler in &self.handlers {
+      handler.dump(out);
+    }
+  }
+
+  /// Sums the dropped messages of all handlers
+  fn dropped(&self) -> u64 {
+    self.handlers.iter().map(|handler| handler.dropped()).sum()
+  }
+
   /// Delegates to the correct handlers for the given log levels
   fn log(&self, record: &Record<'_>) {
-    let now: SystemTime = SystemTime::now();
+    let ctx = LogContext::new(
+      SystemTime::now(),
+      log_context::next_sequence(),
+      self.start,
+      self.global_fields.as_ref(),
+      &self.extensions,
+    );
     if let Some(indices) = self.handler_indices.get(util::get_idx_for_level(record.level())) {
       for idx in indices {
         if let Some(handler) = self.handlers.get(*idx) {
-          handler.log(now, record);
+          handler.log(&ctx, record);
         }
       }
     }
@@ -357,12 +799,20 @@ impl Log for LogImpl {
   fn log(&self, record: &Record<'_>) {
     if let Some(guard) = self.guard() {
       if let Some(inner) = guard.as_ref() {
+        let _logging = panic::Logging::enter();
         inner.log(record);
       }
     }
   }
 
-  fn flush(&self) {}
+  fn flush(&self) {
+    if let Some(guard) = self.guard() {
+      if let Some(inner) = guard.as_ref() {
+        let _logging = panic::Logging::enter();
+        inner.flush();
+      }
+    }
+  }
 }
 
 impl LogImpl {
@@ -0,0 +1,170 @@
+use std::any::Any;
+use std::cell::{Cell, OnceCell};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::Thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::context::GlobalFields;
+use crate::util;
+
+/// Sequence number of the next record.
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+  /// Monotonic time of the previous record of this thread whose delta was read.
+  static PREVIOUS: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+/// Assigns the next sequence number.
+pub fn next_sequence() -> u64 {
+  SEQUENCE.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Values attached to the logger with `Builder::extension`.
+pub type Extensions = Vec<Arc<dyn Any + Send + Sync>>;
+
+/// Information about a record besides the `log::Record` itself, passed to format fns registered with
+/// `Builder::format_with_context`.
+///
+/// Values that are not free to compute (monotonic time, delta, thread) are computed on first use
+/// and then shared by all formats of the same record.
+pub struct LogContext<'a> {
+  /// Wall time of the record
+  now: SystemTime,
+  /// Sequence number of the record
+  sequence: u64,
+  /// When the logger was initialized
+  start: Instant,
+  /// Monotonic time of the record, read on first use
+  instant: OnceCell<Instant>,
+  /// Time since the previous record of the thread, computed on first use
+  delta: OnceCell<Duration>,
+  /// Handle of the current thread, obtained on first use
+  thread: OnceCell<Thread>,
+  /// Fields set with `Builder::global_field`
+  global_fields: Option<&'a GlobalFields>,
+  /// Values set with `Builder::extension`
+  extensions: &'a Extensions,
+}
+
+impl<'a> LogContext<'a> {
+  /// Creates the context for a record logged at `now`.
+  pub(crate) const fn new(
+    now: SystemTime,
+    sequence: u64,
+    start: Instant,
+    global_fields: Option<&'a GlobalFields>,
+    extensions: &'a Extensions,
+  ) -> Self {
+    Self {
+      now,
+      sequence,
+      start,
+      instant: OnceCell::new(),
+      delta: OnceCell::new(),
+      thread: OnceCell::new(),
+      global_fields,
+      extensions,
+    }
+  }
+
+  /// The wall clock time when the record was logged.
+  #[must_use]
+  pub const fn now(&self) -> SystemTime {
+    self.now
+  }
+
+  /// A process wide, monotonically increasing number of the record, starting at 0.
+  /// Every record that reaches at least one format is numbered, all formats of a record see the same number.
+  /// A gap in the numbers an appender received means records were dropped on the way.
+  #[must_use]
+  pub const fn sequence(&self) -> u64 {
+    self.sequence
+  }
+
+  /// Monotonic time when the record was formatted, for measuring durations between records.
+  #[must_use]
+  pub fn instant(&self) -> Instant {
+    *self.instant.get_or_init(Instant::now)
+  }
+
+  /// Monotonic time since the logger was initialized with `Builder::init`.
+  #[must_use]
+  pub fn uptime(&self) -> Duration {
+    self.instant().saturating_duration_since(self.start)
+  }
+
+  /// Monotonic time since the previous record logged by the same thread, zero for the first record of a thread.
+  /// Only records for which the delta was read count as previous records, which is every record if the format always reads it.
+  #[must_use]
+  pub fn delta(&self) -> Duration {
+    *self.delta.get_or_init(|| {
+      let instant = self.instant();
+      let previous = PREVIOUS.try_with(|previous| previous.replace(Some(instant))).ok().flatten();
+      previous.map_or(Duration::ZERO, |previous| instant.saturating_duration_since(previous))
+    })
+  }
+
+  /// The thread that logged the record.
+  #[must_use]
+  pub fn thread(&self) -> &Thread {
+    self.thread.get_or_init(std::thread::current)
+  }
+
+  /// The kernel thread id of the thread that logged the record, only supported on Linux.
+  #[must_use]
+  pub fn os_thread_id(&self) -> Option<u64> {
+    util::os_thread_id()
+  }
+
+  /// Calls `func` for every field set with `Builder::global_field`, in the order they were set.
+  pub fn for_each_global_field(&self, mut func: impl FnMut(&str, &str)) {
+    for (key, value) in self.global_fields.iter().flat_map(|fields| fields.0.iter()) {
+      func(key, value);
+    }
+  }
+
+  /// Returns the value of type `E` set with `Builder::extension`.
+  #[must_use]
+  pub fn extension<E: Any + Send + Sync>(&self) -> Option<&E> {
+    self.extensions.iter().find_map(|extension| extension.downcast_ref::<E>())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::time::{Duration, Instant, UNIX_EPOCH};
+
+  use crate::log_context::{next_sequence, Extensions, LogContext};
+
+  #[test]
+  fn lazy_values() {
+    let extensions: Extensions = vec![std::sync::Arc::new(42_u32)];
+    let start = Instant::now();
+    let ctx = LogContext::new(UNIX_EPOCH, 7, start, None, &extensions);
+
+    assert_eq!(ctx.sequence(), 7);
+    assert_eq!(ctx.instant(), start + ctx.uptime());
+    assert!(next_sequence() < next_sequence());
+    assert_eq!(ctx.instant(), ctx.instant());
+    assert_eq!(ctx.thread().id(), std::thread::current().id());
+    assert_eq!(ctx.extension::<u32>(), Some(&42));
+    assert_eq!(ctx.extension::<u64>(), None);
+  }
+
+  #[test]
+  fn delta_per_thread() {
+    std::thread::spawn(|| {
+      let extensions = Vec::new();
+      let first = LogContext::new(UNIX_EPOCH, 0, Instant::now(), None, &extensions);
+      assert_eq!(first.delta(), Duration::ZERO);
+
+      let second = LogContext::new(UNIX_EPOCH, 1, Instant::now(), None, &extensions);
+      assert_eq!(second.delta(), second.instant() - first.instant());
+      assert_eq!(second.delta(), second.delta());
+    })
+    .join()
+    .expect("thread panicked");
+  }
+}
@@ -0,0 +1,275 @@
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::cell::Cell;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::panic::{self, Location, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use log::{Level, Log, Record};
+
+use crate::TL;
+
+/// A panic hook as returned by `std::panic::take_hook`
+type PanicHook = Box<dyn Fn(&PanicHookInfo<'_>) + Sync + Send + 'static>;
+
+/// State of the installed panic hook.
+struct PanicState {
+  /// The hook that was installed before ours, called after ours and restored by `uninstall`.
+  /// It is shared so the hook can call it without holding the lock.
+  previous: Arc<PanicHook>,
+  /// Log the panic as an error through the configured handlers.
+  log: bool,
+  /// File to dump the buffered messages to.
+  dump_path: Option<PathBuf>,
+}
+
+/// Holds the state while our panic hook is installed.
+static PANIC: Mutex<Option<PanicState>> = Mutex::new(None);
+
+thread_local! {
+  /// Set while the thread is in the appenders, see `Logging`.
+  static LOGGING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks the current thread as being in the appenders until it is dropped.
+///
+/// A panic in an appender may leave the appender's lock held by the panicking thread,
+/// so the hook does not log, flush or dump from a thread that panicked while logging.
+pub struct Logging(bool);
+
+impl Logging {
+  /// Marks the current thread.
+  pub fn enter() -> Self {
+    Self(LOGGING.try_with(|logging| logging.replace(true)).unwrap_or(false))
+  }
+}
+
+impl Drop for Logging {
+  fn drop(&mut self) {
+    _ = LOGGING.try_with(|logging| logging.set(self.0));
+  }
+}
+
+/// Locks the panic state, ignoring poison.
+fn state() -> MutexGuard<'static, Option<PanicState>> {
+  PANIC.lock().unwrap_or_else(|poison| {
+    PANIC.clear_poison();
+    poison.into_inner()
+  })
+}
+
+/// Installs the panic hook if required and lets the fn modify its state.
+fn install(modify: impl FnOnce(&mut PanicState)) {
+  let mut guard = state();
+  if let Some(state) = guard.as_mut() {
+    modify(state);
+    return;
+  }
+
+  if std::thread::panicking() {
+    // set_hook/take_hook panic while panicking.
+    return;
+  }
+
+  let mut state =
+    PanicState { previous: Arc::new(panic::take_hook()), log: false, dump_path: None };
+  modify(&mut state);
+  *guard = Some(state);
+  panic::set_hook(Box::new(hook));
+  drop(guard);
+}
+
+/// Installs a panic hook that logs panics through `trivial_log`.
+///
+/// The panic message, location, thread name and a backtrace (if enabled with `RUST_BACKTRACE`, see `std::backtrace::Backtrace::capture`)
+/// are logged as `Level::Error` with the target "panic" through the configured handlers.
+/// Afterward all appenders are flushed and the previously installed panic hook is called.
+/// A panic inside an appender is not logged, the appender may still hold its lock, only the previous hook is called.
+///
+/// `trivial_log::free()` uninstalls the hook and restores the previous one.
+/// Calling this fn repeatedly has no additional effect.
+pub fn install_panic_hook() {
+  install(|state| state.log = true);
+}
+
+/// Sets the file the panic hook dumps to, installing the hook if required.
+pub fn set_dump_path(path: Option<PathBuf>) {
+  if path.is_none() && state().is_none() {
+    return;
+  }
+
+  install(|state| state.dump_path = path);
+}
+
+/// Restores the previous panic hook if ours is installed.
+pub fn uninstall() {
+  if std::thread::panicking() {
+    // set_hook/take_hook panic while panicking.
+    return;
+  }
+
+  let mut guard = state();
+  if let Some(state) = guard.take() {
+    // Drops our hook.
+    drop(panic::take_hook());
+    // Only a hook that is running on another thread still holds a reference.
+    let previous = Arc::try_unwrap(state.previous)
+      .unwrap_or_else(|previous| Box::new(move |info: &PanicHookInfo<'_>| previous(info)));
+    panic::set_hook(previous);
+  }
+}
+
+/// The panic hook, the state is copied so no lock is held while logging or calling the previous hook.
+fn hook(info: &PanicHookInfo<'_>) {
+  let Some((log_panic, dump_path, previous)) =
+    state().as_ref().map(|state| (state.log, state.dump_path.clone(), Arc::clone(&state.previous)))
+  else {
+    return;
+  };
+
+  if !LOGGING.try_with(Cell::get).unwrap_or(true) {
+    if log_panic {
+      log(info);
+    }
+
+    if let Some(path) = dump_path {
+      dump(&path);
+    }
+  }
+
+  previous(info);
+}
+
+/// Logs the panic as an error and flushes all appenders.
+fn log(info: &PanicHookInfo<'_>) {
+  let payload = info.payload();
+  let message = payload
+    .downcast_ref::<&str>()
+    .copied()
+    .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+    .unwrap_or("Box<dyn Any>");
+
+  let thread = std::thread::current();
+  let name = thread.name().unwrap_or("<unnamed>");
+
+  let location = info.location();
+  let file = location.map(Location::file);
+  let line = location.map(Location::line);
+  let column = location.map_or(0, Location::column);
+
+  let backtrace = Backtrace::capture();
+  let mut record = Record::builder();
+  record.level(Level::Error).target("panic").file(file).line(line);
+
+  if backtrace.status() == BacktraceStatus::Captured {
+    TL.log(
+      &record
+        .args(format_args!(
+          "thread '{name}' panicked at {}:{}:{column}:\n{message}\nstack backtrace:\n{backtrace}",
+          file.unwrap_or("<unknown>"),
+          line.unwrap_or(0)
+        ))
+        .build(),
+    );
+  } else {
+    TL.log(
+      &record
+        .args(format_args!(
+          "thread '{name}' panicked at {}:{}:{column}:\n{message}",
+          file.unwrap_or("<unknown>"),
+          line.unwrap_or(0)
+        ))
+        .build(),
+    );
+  }
+
+  TL.flush();
+}
+
+/// Writes the buffered messages of all appenders to the file.
+fn dump(path: &Path) {
+  let Some(guard) = TL.guard() else {
+    return;
+  };
+  let Some(inner) = guard.as_ref() else {
+    return;
+  };
+  let Ok(mut file) = OpenOptions::new().append(true).create(true).open(path) else {
+    return;
+  };
+
+  inner.dump(&mut file);
+  _ = file.flush();
+}
+
+#[cfg(test)]
+mod test {
+  use std::panic;
+  use std::sync::Mutex;
+  use std::thread;
+
+  use log::LevelFilter;
+
+  use crate::StdoutAppender;
+
+  /// Initializes a logger that writes the target and message of errors to stdout.
+  fn init() {
+    crate::builder()
+      .format(
+        |_, record| Some(format!("logged {}: {}\n", record.target(), record.args())),
+        |builder| builder.appender_filter(LevelFilter::Error, StdoutAppender::new()),
+      )
+      .init()
+      .expect("init");
+  }
+
+  /// Panics on a thread named "worker".
+  fn panic_on_worker(message: &'static str) {
+    let worker =
+      thread::Builder::new().name("worker".to_string()).spawn(move || panic!("{message}"));
+    assert!(worker.expect("spawn").join().is_err());
+  }
+
+  /// Panics with and without our hook, started by `panic_hook`.
+  #[test]
+  #[ignore = "started in a child process by `panic_hook`"]
+  fn panic_child() {
+    panic::set_hook(Box::new(|_| eprintln!("previous hook")));
+
+    init();
+    crate::install_panic_hook();
+    panic_on_worker("first");
+    crate::free();
+
+    // free() restored the previous hook, so the panic is not logged.
+    init();
+    panic_on_worker("second");
+    crate::free();
+
+    // The appender still holds its lock while the hook runs, logging the panic would deadlock.
+    let lock = Mutex::new(());
+    crate::builder()
+      .default_format(|builder| {
+        builder.appender_filter(LevelFilter::Error, move |_: &String| {
+          let _held = lock.lock();
+          panic!("third");
+        })
+      })
+      .init()
+      .expect("init");
+    crate::install_panic_hook();
+    assert!(thread::spawn(|| log::error!("boom")).join().is_err());
+    crate::free();
+  }
+
+  #[test]
+  fn panic_hook() {
+    let (out, err) = crate::child::run("panic::test::panic_child", &[]);
+    assert!(out.contains("logged panic: thread 'worker' panicked at src/panic.rs:"), "{out}");
+    assert!(out.contains(":\nfirst\n"), "{out}");
+    assert!(!out.contains("second"), "{out}");
+    assert!(!out.contains("third"), "{out}");
+    assert_eq!(err.matches("previous hook\n").count(), 3, "{err}");
+  }
+}
@@ -0,0 +1,379 @@
+use std::borrow::Cow;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use log::{Level, Record};
+
+use crate::{util, Appender, FormatFn, IntoAppender, LogContext};
+
+/// Decides which records share a token bucket in `RateLimited`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum RateLimitKey {
+  /// One bucket per `Record::target()`
+  #[default]
+  Target,
+  /// One bucket per callsite `file:line`
+  Callsite,
+}
+
+/// The key of a bucket, the file and line are only set for `RateLimitKey::Callsite`.
+///
+/// The strings are borrowed if the record has them as `&'static str`, as records created by the `log` macros do.
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+struct Key {
+  /// `Record::target()`
+  target: Cow<'static, str>,
+  /// `Record::file()`
+  file: Option<Cow<'static, str>>,
+  /// `Record::line()`
+  line: Option<u32>,
+}
+
+impl Key {
+  /// Returns the key of the record.
+  fn of(record: &Record<'_>, kind: RateLimitKey) -> Self {
+    // The target defaults to the module path, which is static.
+    let target = match record.module_path_static() {
+      Some(module_path) if module_path == record.target() => Cow::Borrowed(module_path),
+      _ => Cow::Owned(record.target().to_string()),
+    };
+    if kind == RateLimitKey::Target {
+      return Self { target, file: None, line: None };
+    }
+
+    let file = match (record.file_static(), record.file()) {
+      (Some(file), _) => Some(Cow::Borrowed(file)),
+      (None, file) => file.map(|file| Cow::Owned(file.to_string())),
+    };
+    Self { target, file, line: record.line() }
+  }
+}
+
+impl fmt::Display for Key {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match (&self.file, self.line) {
+      (None, None) => f.write_str(&self.target),
+      (file, line) => {
+        write!(f, "{}:{}", file.as_deref().unwrap_or("<unknown>"), line.unwrap_or(0))
+      }
+    }
+  }
+}
+
+/// Token bucket state for a single key.
+struct Bucket {
+  /// Theoretical arrival time of the next message. The bucket is full if this is in the past.
+  tat: Instant,
+  /// Number of messages dropped since `since`.
+  suppressed: u64,
+  /// Start of the current suppression window.
+  since: Instant,
+}
+
+/// Mutable state of `RateLimited`
+struct State {
+  /// Buckets by key, buckets that refilled are removed when the summaries are collected
+  buckets: HashMap<Key, Bucket>,
+  /// Next point in time where summaries for suppressed messages are emitted.
+  next_report: Instant,
+}
+
+/// Appender wrapper that drops messages once a target or callsite exceeds its rate.
+///
+/// Each key gets a token bucket that allows `burst` messages and refills continuously
+/// so that at most `burst` messages per `period` are forwarded in the long run.
+/// Dropped messages are counted and once per report interval (default: `period`) a synthesized
+/// "suppressed N messages from X in the last Ys" record is formatted and sent to the wrapped appender.
+///
+/// Summaries are only emitted while messages are being logged or when the appender is flushed,
+/// this wrapper does not start any threads.
+pub struct RateLimited<T> {
+  /// The wrapped appender
+  inner: Arc<dyn Appender<T>>,
+  /// Format fn for the synthesized summary records
+  summary: Box<FormatFn<T>>,
+  /// How records are grouped into buckets
+  key: RateLimitKey,
+  /// Time each message "costs", `period / burst`
+  interval: Duration,
+  /// Size of the bucket in time, `period`
+  period: Duration,
+  /// How often summaries are emitted
+  report_interval: Duration,
+  /// Buckets and reporting state
+  state: Mutex<State>,
+  /// Number of suppressed messages since the appender was created
+  dropped: AtomicU64,
+}
+
+impl<T> RateLimited<T> {
+  /// Wraps `inner` to allow at most `burst` messages per key every `period`.
+  /// Summary records are formatted using the default format.
+  #[must_use]
+  pub fn new(inner: impl IntoAppender<T>, burst: u32, period: Duration) -> Self
+  where
+    T: From<String>,
+  {
+    Self::with_summary_format(inner, burst, period, |now, record| {
+      util::default_format(now, record).map(T::from)
+    })
+  }
+
+  /// Wraps `inner` to allow at most `burst` messages per key every `period`.
+  /// The given format fn is used to turn the synthesized summary record into a `T`.
+  #[must_use]
+  pub fn with_summary_format(
+    inner: impl IntoAppender<T>,
+    burst: u32,
+    period: Duration,
+    summary: impl Fn(SystemTime, &Record<'_>) -> Option<T> + Send + Sync + 'static,
+  ) -> Self {
+    let now = Instant::now();
+    Self {
+      inner: inner.into_appender(),
+      summary: Box::new(summary),
+      key: RateLimitKey::Target,
+      interval: period / burst.max(1),
+      period,
+      report_interval: period,
+      state: Mutex::new(State { buckets: HashMap::new(), next_report: now + period }),
+      dropped: AtomicU64::new(0),
+    }
+  }
+
+  /// Sets how records are grouped into buckets. Defaults to `RateLimitKey::Target`.
+  #[must_use]
+  pub const fn key(mut self, key: RateLimitKey) -> Self {
+    self.key = key;
+    self
+  }
+
+  /// Sets how often summaries for suppressed messages are emitted. Defaults to `period`.
+  #[must_use]
+  pub const fn report_interval(mut self, report_interval: Duration) -> Self {
+    self.report_interval = report_interval;
+    self
+  }
+
+  /// Handles a single record at the given point in time.
+  fn append_at(
+    &self,
+    now: Instant,
+    ctx: Option<&LogContext<'_>>,
+    record: &Record<'_>,
+    message: &T,
+  ) {
+    let mut summaries = Vec::new();
+    let allowed = {
+      let Ok(mut state) = self.state.lock() else {
+        return;
+      };
+
+      let allowed = self.take_token(&mut state, now, Key::of(record, self.key));
+
+      if now >= state.next_report {
+        state.next_report = now + self.report_interval;
+        Self::collect_summaries(&mut state, now, &mut summaries);
+      }
+
+      allowed
+    };
+
+    // Summaries come first, they describe what happened before this message.
+    self.emit_summaries(summaries);
+    if allowed {
+      util::forward(self.inner.as_ref(), ctx, record, message);
+    } else {
+      self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  /// Takes a token from the bucket of the key, returns false if the bucket is empty.
+  fn take_token(&self, state: &mut State, now: Instant, key: Key) -> bool {
+    let bucket = match state.buckets.entry(key) {
+      Entry::Occupied(entry) => entry.into_mut(),
+      Entry::Vacant(entry) => {
+        entry.insert(Bucket { tat: now + self.interval, suppressed: 0, since: now });
+        return true;
+      }
+    };
+
+    let tat = std::cmp::max(bucket.tat, now) + self.interval;
+    if tat.saturating_duration_since(now) > self.period {
+      if bucket.suppressed == 0 {
+        bucket.since = now;
+      }
+      bucket.suppressed += 1;
+      return false;
+    }
+
+    bucket.tat = tat;
+    true
+  }
+
+  /// Resets all buckets with suppressed messages and returns (key, count, window) for each of them.
+  /// Buckets that are full again are removed, a new bucket for the key starts out full as well.
+  fn collect_summaries(state: &mut State, now: Instant, out: &mut Vec<(Key, u64, Duration)>) {
+    state.buckets.retain(|key, bucket| {
+      if bucket.suppressed > 0 {
+        out.push((key.clone(), bucket.suppressed, now.saturating_duration_since(bucket.since)));
+        bucket.suppressed = 0;
+      }
+      bucket.tat > now
+    });
+  }
+
+  /// Formats and appends a summary record for each entry.
+  fn emit_summaries(&self, summaries: Vec<(Key, u64, Duration)>) {
+    if summaries.is_empty() {
+      return;
+    }
+
+    let now = SystemTime::now();
+    for (key, count, window) in summaries {
+      self.emit_summary(
+        now,
+        &key.target,
+        format_args!(
+          "suppressed {count} messages from {key} in the last {:.1}s",
+          window.as_secs_f64()
+        ),
+      );
+    }
+  }
+
+  /// Formats and appends a single synthesized summary record.
+  fn emit_summary(&self, now: SystemTime, target: &str, args: fmt::Arguments<'_>) {
+    let record = Record::builder().level(Level::Warn).target(target).args(args).build();
+    if let Some(fmt) = (self.summary)(now, &record) {
+      self.inner.append_log_record(&record, &fmt);
+    }
+  }
+}
+
+impl<T> Appender<T> for RateLimited<T> {
+  fn append_log_message(&self, message: &T) {
+    // Without a record there is no key, treat it like a record without target/callsite.
+    self.append_log_record(&Record::builder().args(format_args!("")).build(), message);
+  }
+
+  fn append_log_record(&self, record: &Record<'_>, message: &T) {
+    self.append_at(Instant::now(), None, record, message);
+  }
+
+  fn append_log_context(&self, ctx: &LogContext<'_>, record: &Record<'_>, message: &T) {
+    self.append_at(Instant::now(), Some(ctx), record, message);
+  }
+
+  fn flush(&self) {
+    let mut summaries = Vec::new();
+    if let Ok(mut state) = self.state.lock() {
+      let now = Instant::now();
+      state.next_report = now + self.report_interval;
+      Self::collect_summaries(&mut state, now, &mut summaries);
+    }
+
+    self.emit_summaries(summaries);
+    self.inner.flush();
+  }
+
+  fn dump(&self, out: &mut dyn io::Write) {
+    self.inner.dump(out);
+  }
+
+  fn dropped(&self) -> u64 {
+    self.dropped.load(Ordering::Relaxed) + self.inner.dropped()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::sync::{Arc, Mutex};
+  use std::time::{Duration, Instant};
+
+  use log::Record;
+
+  use crate::{Appender, RateLimitKey, RateLimited};
+
+  fn collector() -> (Arc<Mutex<Vec<String>>>, impl Fn(&String) + Send + Sync + 'static) {
+    let out = Arc::new(Mutex::new(Vec::new()));
+    let out2 = Arc::clone(&out);
+    (out, move |msg: &String| out2.lock().expect("poisoned").push(msg.clone()))
+  }
+
+  #[test]
+  fn burst_then_summary() {
+    let (out, appender) = collector();
+    let limited =
+      RateLimited::with_summary_format(appender, 3, Duration::from_secs(1), |_, rec| {
+        Some(rec.args().to_string())
+      });
+
+    let start = Instant::now();
+    for i in 0..10 {
+      let msg = format!("msg {i}");
+      limited.append_at(start, None, &Record::builder().target("hot").build(), &msg);
+    }
+    // A different target has its own bucket.
+    limited.append_at(start, None, &Record::builder().target("cold").build(), &"cold".to_string());
+
+    assert_eq!(*out.lock().expect("poisoned"), vec!["msg 0", "msg 1", "msg 2", "cold"]);
+    assert_eq!(limited.dropped(), 7);
+
+    // After the period, the bucket is refilled and the summary is emitted before the message.
+    let later = start + Duration::from_secs(1);
+    limited.append_at(later, None, &Record::builder().target("hot").build(), &"again".to_string());
+    assert_eq!(
+      out.lock().expect("poisoned").get(4..).expect("missing"),
+      ["suppressed 7 messages from hot in the last 1.0s", "again"]
+    );
+  }
+
+  #[test]
+  fn per_callsite() {
+    let (out, appender) = collector();
+    let limited =
+      RateLimited::<String>::new(appender, 1, Duration::from_secs(30)).key(RateLimitKey::Callsite);
+
+    let start = Instant::now();
+    for line in [1, 1, 2, 2, 1] {
+      let record = Record::builder().target("same").file(Some("a.rs")).line(Some(line)).build();
+      limited.append_at(start, None, &record, &format!("line {line}"));
+    }
+
+    assert_eq!(*out.lock().expect("poisoned"), vec!["line 1", "line 2"]);
+
+    // Summaries name the callsite.
+    let later = start + Duration::from_secs(30);
+    limited.append_at(later, None, &Record::builder().target("same").build(), &"x".to_string());
+    let out = out.lock().expect("poisoned").clone();
+    for summary in ["2 messages from a.rs:1", "1 messages from a.rs:2"] {
+      let expected = format!(" - suppressed {summary} in the last 30.0s\n");
+      assert!(out.iter().any(|msg| msg.ends_with(&expected)), "{out:?}");
+    }
+  }
+
+  #[test]
+  fn evicts_full_buckets() {
+    let (_out, appender) = collector();
+    let limited = RateLimited::<String>::new(appender, 2, Duration::from_secs(1));
+
+    let start = Instant::now();
+    for target in ["a", "b", "c"] {
+      limited.append_at(start, None, &Record::builder().target(target).build(), &String::new());
+    }
+    assert_eq!(limited.state.lock().expect("poisoned").buckets.len(), 3);
+
+    // A period later the buckets are full again, the report removes them.
+    let later = start + Duration::from_secs(1);
+    limited.append_at(later, None, &Record::builder().target("d").build(), &String::new());
+    let keys: Vec<_> =
+      limited.state.lock().expect("poisoned").buckets.keys().map(ToString::to_string).collect();
+    assert_eq!(keys, ["d"]);
+  }
+}
@@ -0,0 +1,223 @@
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
+
+use log::{Level, Record};
+
+use crate::{Appender, IntoAppender, LogContext};
+
+/// Writes a message to the panic dump
+type EncodeFn<T> = fn(&T, &mut dyn Write) -> io::Result<()>;
+
+/// A buffered message and what is known about the record it was formatted from.
+struct Entry<T> {
+  /// The formatted message
+  message: T,
+  /// Level of the record, `None` if the message was appended without one
+  level: Option<Level>,
+  /// Time and sequence number of the record, `None` if the message was appended without a context
+  context: Option<(SystemTime, u64)>,
+}
+
+impl<T> Entry<T> {
+  /// Appends the message to `target` with as much of the original record as was kept.
+  /// The target of the record is not kept.
+  fn append_to(&self, target: &dyn Appender<T>) {
+    let Some(level) = self.level else {
+      target.append_log_message(&self.message);
+      return;
+    };
+
+    let record = Record::builder().level(level).build();
+    match self.context {
+      Some((now, sequence)) => {
+        let extensions = Vec::new();
+        let ctx = LogContext::new(now, sequence, Instant::now(), None, &extensions);
+        target.append_log_context(&ctx, &record, &self.message);
+      }
+      None => target.append_log_record(&record, &self.message),
+    }
+  }
+}
+
+/// Appender that keeps the last N messages in memory, like a flight recorder.
+///
+/// The buffer can be dumped to another appender whenever a record of a given level (usually `Level::Error`)
+/// is appended, which gives context around failures without persisting all Trace/Debug messages.
+/// It is also dumped by the panic hook installed by `Builder::dump_on_panic`.
+/// Dumped messages are removed from the buffer.
+pub struct RingBufferAppender<T> {
+  /// Maximum number of messages in the buffer
+  capacity: usize,
+  /// The messages, oldest first
+  buffer: Mutex<VecDeque<Entry<T>>>,
+  /// Records of this level or more severe dump the buffer into `dump_to`
+  dump_level: Level,
+  /// Where the buffer is dumped to when a record of `dump_level` is appended
+  dump_to: Option<Arc<dyn Appender<T>>>,
+  /// Turns a message into bytes for the panic dump
+  encode: EncodeFn<T>,
+  /// Number of messages that were evicted before they were dumped
+  dropped: AtomicU64,
+}
+
+impl<T: Clone> RingBufferAppender<T> {
+  /// Creates a ring buffer for the last `capacity` messages.
+  #[must_use]
+  pub fn new(capacity: usize) -> Self
+  where
+    T: AsRef<[u8]>,
+  {
+    Self::with_encoder(capacity, |message, out| out.write_all(message.as_ref()))
+  }
+
+  /// Creates a ring buffer for the last `capacity` messages.
+  /// The encoder is used to write messages to the file of `Builder::dump_on_panic`.
+  #[must_use]
+  pub fn with_encoder(capacity: usize, encode: EncodeFn<T>) -> Self {
+    Self {
+      capacity,
+      buffer: Mutex::new(VecDeque::with_capacity(capacity)),
+      dump_level: Level::Error,
+      dump_to: None,
+      encode,
+      dropped: AtomicU64::new(0),
+    }
+  }
+
+  /// Dumps the buffer into `target` whenever a record of `level` or more severe is appended.
+  /// The record that caused the dump is the last message in the dump.
+  #[must_use]
+  pub fn dump_on(mut self, level: Level, target: impl IntoAppender<T>) -> Self {
+    self.dump_level = level;
+    self.dump_to = Some(target.into_appender());
+    self
+  }
+
+  /// Dumps the buffer into `target` whenever an error is logged.
+  #[must_use]
+  pub fn dump_on_error(self, target: impl IntoAppender<T>) -> Self {
+    self.dump_on(Level::Error, target)
+  }
+
+  /// Removes all messages from the buffer and appends them to `target`.
+  /// Messages are appended with the level, time and sequence number of their records, the target is not kept.
+  pub fn dump_into(&self, target: &dyn Appender<T>) {
+    let entries = self.take();
+    for entry in &entries {
+      entry.append_to(target);
+    }
+  }
+
+  /// Returns a copy of the messages currently in the buffer, oldest first.
+  #[must_use]
+  pub fn messages(&self) -> Vec<T> {
+    self
+      .buffer
+      .lock()
+      .map(|buffer| buffer.iter().map(|entry| entry.message.clone()).collect())
+      .unwrap_or_default()
+  }
+
+  /// Removes all messages from the buffer.
+  fn take(&self) -> VecDeque<Entry<T>> {
+    self.buffer.lock().map(|mut buffer| std::mem::take(&mut *buffer)).unwrap_or_default()
+  }
+
+  /// Adds the message to the buffer, evicting the oldest if it is full.
+  fn push(&self, entry: Entry<T>) {
+    if self.capacity == 0 {
+      self.dropped.fetch_add(1, Ordering::Relaxed);
+      return;
+    }
+
+    if let Ok(mut buffer) = self.buffer.lock() {
+      if buffer.len() >= self.capacity && buffer.pop_front().is_some() {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+      }
+      buffer.push_back(entry);
+    }
+  }
+
+  /// Buffers the message and dumps the buffer if the level requires it.
+  fn append(&self, level: Level, context: Option<(SystemTime, u64)>, message: &T) {
+    self.push(Entry { message: message.clone(), level: Some(level), context });
+    if level <= self.dump_level {
+      if let Some(target) = self.dump_to.as_ref() {
+        // The lock is released before appending so a slow target does not block logging threads.
+        self.dump_into(target.as_ref());
+      }
+    }
+  }
+}
+
+impl<T: Clone + Send + Sync> Appender<T> for RingBufferAppender<T> {
+  fn append_log_message(&self, message: &T) {
+    self.push(Entry { message: message.clone(), level: None, context: None });
+  }
+
+  fn append_log_record(&self, record: &Record<'_>, message: &T) {
+    self.append(record.level(), None, message);
+  }
+
+  fn append_log_context(&self, ctx: &LogContext<'_>, record: &Record<'_>, message: &T) {
+    self.append(record.level(), Some((ctx.now(), ctx.sequence())), message);
+  }
+
+  fn flush(&self) {
+    if let Some(target) = self.dump_to.as_ref() {
+      target.flush();
+    }
+  }
+
+  fn dump(&self, out: &mut dyn Write) {
+    // A panic while the lock is held by this thread would otherwise deadlock.
+    let Ok(mut buffer) = self.buffer.try_lock() else {
+      return;
+    };
+
+    for entry in buffer.drain(..) {
+      _ = (self.encode)(&entry.message, out);
+    }
+  }
+
+  fn dropped(&self) -> u64 {
+    let dumped = self.dump_to.as_ref().map_or(0, |target| target.dropped());
+    self.dropped.load(Ordering::Relaxed) + dumped
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::sync::{Arc, Mutex};
+
+  use log::{Level, Record};
+
+  use crate::{Appender, RingBufferAppender};
+
+  #[test]
+  fn keeps_last_n_and_dumps_on_error() {
+    let dumped = Arc::new(Mutex::new(Vec::new()));
+    let dumped2 = Arc::clone(&dumped);
+    let ring = RingBufferAppender::new(3)
+      .dump_on_error(move |msg: &String| dumped2.lock().expect("poisoned").push(msg.clone()));
+
+    for i in 0..5 {
+      ring.append_log_record(&Record::builder().level(Level::Debug).build(), &format!("debug {i}"));
+    }
+    assert_eq!(ring.messages(), ["debug 2", "debug 3", "debug 4"]);
+    assert!(dumped.lock().expect("poisoned").is_empty());
+
+    ring.append_log_record(&Record::builder().level(Level::Error).build(), &"error".to_string());
+    assert_eq!(*dumped.lock().expect("poisoned"), ["debug 3", "debug 4", "error"]);
+    assert!(ring.messages().is_empty());
+    assert_eq!(ring.dropped(), 3);
+
+    ring.append_log_record(&Record::builder().level(Level::Info).build(), &"info".to_string());
+    let mut out = Vec::new();
+    ring.dump(&mut out);
+    assert_eq!(out, b"info");
+  }
+}
@@ -0,0 +1,582 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+#[cfg(feature = "compression")]
+use std::sync::{mpsc, OnceLock};
+#[cfg(feature = "compression")]
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use crate::Appender;
+
+/// The open file and how much was written to it.
+struct Current {
+  /// The file at `path`
+  file: File,
+  /// Size of the file in bytes
+  size: u64,
+}
+
+/// The archives of a file and how long they are kept, shared with the compression thread.
+#[derive(Debug, Clone)]
+struct Archives {
+  /// Path of the current file, archives get a numeric suffix
+  path: PathBuf,
+  /// Number of archives to keep
+  keep: usize,
+  /// Archives beyond this total size in bytes are deleted, the newest are kept
+  max_total_size: Option<u64>,
+  /// Archives last modified longer ago are deleted
+  max_age: Option<Duration>,
+  /// Archives are gzipped, `app.log.1.gz`
+  #[cfg(feature = "compression")]
+  compress: bool,
+}
+
+impl Archives {
+  /// Returns the path of the nth archive, `app.log.n` or `app.log.n.gz`.
+  fn archive(&self, n: usize) -> PathBuf {
+    let mut path = self.path.clone().into_os_string();
+    path.push(format!(".{n}"));
+    #[cfg(feature = "compression")]
+    if self.compress {
+      path.push(".gz");
+    }
+    PathBuf::from(path)
+  }
+
+  /// Shifts the archives by one, deleting the oldest, so the first archive is free.
+  fn shift(&self) {
+    // Errors are ignored, gaps in the archives are allowed.
+    _ = fs::remove_file(self.archive(self.keep));
+    for n in (1..self.keep).rev() {
+      _ = fs::rename(self.archive(n), self.archive(n + 1));
+    }
+  }
+
+  /// Deletes the archives beyond `max_total_size` or older than `max_age`.
+  fn cleanup(&self) {
+    if self.max_total_size.is_none() && self.max_age.is_none() {
+      return;
+    }
+
+    let now = SystemTime::now();
+    let mut total = 0_u64;
+    for n in 1..=self.keep {
+      let archive = self.archive(n);
+      let Ok(metadata) = fs::metadata(&archive) else {
+        continue;
+      };
+      total = total.saturating_add(metadata.len());
+      let too_large = self.max_total_size.is_some_and(|max| total > max);
+      let too_old = self.max_age.is_some_and(|max| {
+        metadata.modified().ok().and_then(|modified| now.duration_since(modified).ok()) > Some(max)
+      });
+      if too_large || too_old {
+        _ = fs::remove_file(&archive);
+      }
+    }
+  }
+
+  /// Returns the files left behind by a compression that did not finish, such as after a crash, oldest first.
+  ///
+  /// Only files named like the ones `archive_current` creates, `app.log.<pid>-<n>.pending`, are returned.
+  /// Files of this process and of processes that are still running (checked on Linux) are left alone,
+  /// as are files modified within `STALE_AFTER`, in case another process is about to compress them.
+  #[cfg(feature = "compression")]
+  fn stale(&self) -> Vec<PathBuf> {
+    /// Pending files modified more recently than this are not claimed.
+    const STALE_AFTER: Duration = Duration::from_secs(60);
+
+    let (Some(dir), Some(name)) = (self.path.parent(), self.path.file_name()) else {
+      return Vec::new();
+    };
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let Ok(entries) = fs::read_dir(dir) else {
+      return Vec::new();
+    };
+
+    let now = SystemTime::now();
+    let name = name.to_string_lossy();
+    let mut stale: Vec<_> = entries
+      .filter_map(Result::ok)
+      .filter(|entry| {
+        pending_pid(&name, &entry.file_name().to_string_lossy())
+          .is_some_and(|pid| pid != std::process::id() && !running(pid))
+      })
+      .filter_map(|entry| {
+        let modified = entry.metadata().and_then(|metadata| metadata.modified()).ok()?;
+        let age = now.duration_since(modified).unwrap_or_default();
+        (age >= STALE_AFTER).then(|| (modified, entry.path()))
+      })
+      .collect();
+    stale.sort();
+    stale.into_iter().map(|(_, path)| path).collect()
+  }
+
+  /// Compresses a file that was moved away from `path` into the first archive and deletes it.
+  /// The file is left in place if it cannot be compressed.
+  #[cfg(feature = "compression")]
+  fn compress(&self, pending: &Path) -> io::Result<()> {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    let mut input = File::open(pending)?;
+    let modified = input.metadata()?.modified()?;
+    self.shift();
+    let archive = File::create(self.archive(1))?;
+    let mut encoder = GzEncoder::new(io::BufWriter::new(&archive), Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.flush()?;
+    // The age of an archive is the time of its last message, not of its compression.
+    _ = archive.set_modified(modified);
+    drop(input);
+    fs::remove_file(pending)?;
+    self.cleanup();
+    Ok(())
+  }
+}
+
+/// Returns the process id of a pending file of `name`, `app.log.<pid>-<n>.pending`.
+#[cfg(feature = "compression")]
+fn pending_pid(name: &str, file_name: &str) -> Option<u32> {
+  let rest = file_name.strip_prefix(name)?.strip_prefix('.')?.strip_suffix(".pending")?;
+  let (pid, n) = rest.split_once('-')?;
+  let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+  (is_number(pid) && is_number(n)).then(|| pid.parse().ok()).flatten()
+}
+
+/// Returns true if the process is still running, unknown processes are assumed to have exited.
+#[cfg(feature = "compression")]
+fn running(pid: u32) -> bool {
+  #[cfg(target_os = "linux")]
+  return Path::new("/proc").join(pid.to_string()).exists();
+  #[cfg(not(target_os = "linux"))]
+  {
+    _ = pid;
+    false
+  }
+}
+
+/// Thread that compresses rotated files, so logging is not blocked while it compresses.
+#[cfg(feature = "compression")]
+struct Compressor {
+  /// Sends rotated files to the thread, `None` if it could not be started
+  sender: Option<mpsc::Sender<PathBuf>>,
+  /// The thread, joined on drop so no archive is left uncompressed
+  thread: Option<JoinHandle<()>>,
+  /// Used when the thread could not be started
+  archives: Archives,
+}
+
+#[cfg(feature = "compression")]
+impl Compressor {
+  /// Starts the thread and queues the files a previous compression left behind.
+  fn spawn(archives: Archives) -> Self {
+    let stale = archives.stale();
+    let (sender, receiver) = mpsc::channel::<PathBuf>();
+    let worker = archives.clone();
+    let spawned =
+      thread::Builder::new().name("trivial_log-compress".to_string()).spawn(move || {
+        // Files are compressed in the order they were rotated,
+        // those that failed are retried with the next one.
+        let mut failed = Vec::new();
+        for pending in receiver {
+          failed.push(pending);
+          failed.retain(|pending| worker.compress(pending).is_err() && pending.exists());
+        }
+      });
+    let compressor = match spawned {
+      Ok(thread) => Self { sender: Some(sender), thread: Some(thread), archives },
+      Err(_) => Self { sender: None, thread: None, archives },
+    };
+    for pending in stale {
+      compressor.compress(pending);
+    }
+    compressor
+  }
+
+  /// Compresses the file on the thread, or right away if it is not running.
+  fn compress(&self, pending: PathBuf) {
+    let pending = match &self.sender {
+      Some(sender) => match sender.send(pending) {
+        Ok(()) => return,
+        Err(mpsc::SendError(pending)) => pending,
+      },
+      None => pending,
+    };
+    _ = self.archives.compress(&pending);
+  }
+}
+
+#[cfg(feature = "compression")]
+impl Drop for Compressor {
+  fn drop(&mut self) {
+    // Closing the channel ends the thread once it compressed everything.
+    self.sender = None;
+    if let Some(thread) = self.thread.take() {
+      _ = thread.join();
+    }
+  }
+}
+
+/// Appender that writes to a file and rotates it once it reaches a maximum size.
+///
+/// On rotation `app.log` is renamed to `app.log.1`, `app.log.1` to `app.log.2` and so on,
+/// the oldest archive beyond `keep` is deleted and a new `app.log` is started.
+/// A single message larger than the maximum size is written to a file of its own.
+/// Archives can also be limited by their total size and their age, this is checked on rotation.
+///
+/// With the `compression` feature `compress` gzips the archives to `app.log.1.gz`.
+/// The rotated file is closed and renamed before a background thread compresses it,
+/// the file that is being written to is never touched.
+/// A file that could not be compressed is retried on the next rotation,
+/// files left uncompressed by a previous process are compressed on the first rotation.
+///
+///```rust
+/// use log::LevelFilter;
+/// use trivial_log::RollingFileAppender;
+/// let path = std::env::temp_dir().join("trivial_log_rolling_doc.log");
+/// trivial_log::builder()
+///   .default_format(|builder| {
+///     builder.appender_filter(LevelFilter::Info, RollingFileAppender::new(&path, 10 * 1024 * 1024).unwrap().keep(3))
+///   })
+///   .init()
+///   .unwrap();
+/// log::info!("Hello");
+/// trivial_log::free();
+///```
+pub struct RollingFileAppender {
+  /// The current file and its archives
+  archives: Archives,
+  /// Size in bytes after which the file is rotated
+  max_size: u64,
+  /// The current file, `None` if it could not be reopened after a rotation, only reopened by the next message
+  current: Mutex<Option<Current>>,
+  /// Number of messages that could not be written
+  dropped: AtomicU64,
+  /// Started on the first rotation with compression
+  #[cfg(feature = "compression")]
+  compressor: OnceLock<Compressor>,
+}
+
+impl RollingFileAppender {
+  /// Opens (or creates) the file at `path` for appending, it is rotated once it would exceed `max_size` bytes.
+  /// Five archives are kept by default.
+  /// # Errors
+  /// If the file cannot be opened.
+  pub fn new(path: impl AsRef<Path>, max_size: u64) -> io::Result<Self> {
+    let path = path.as_ref().to_path_buf();
+    let current = Self::open(&path)?;
+    Ok(Self {
+      archives: Archives {
+        path,
+        keep: 5,
+        max_total_size: None,
+        max_age: None,
+        #[cfg(feature = "compression")]
+        compress: false,
+      },
+      max_size,
+      current: Mutex::new(Some(current)),
+      dropped: AtomicU64::new(0),
+      #[cfg(feature = "compression")]
+      compressor: OnceLock::new(),
+    })
+  }
+
+  /// Sets the number of archives to keep. 0 deletes the file on rotation.
+  #[must_use]
+  pub const fn keep(mut self, keep: usize) -> Self {
+    self.archives.keep = keep;
+    self
+  }
+
+  /// Deletes the oldest archives once all archives together are larger than `max_total_size` bytes.
+  #[must_use]
+  pub const fn max_total_size(mut self, max_total_size: u64) -> Self {
+    self.archives.max_total_size = Some(max_total_size);
+    self
+  }
+
+  /// Deletes archives whose last message was written longer than `max_age` ago.
+  /// This is checked on rotation, archives are not deleted while the file does not grow.
+  #[must_use]
+  pub const fn max_age(mut self, max_age: Duration) -> Self {
+    self.archives.max_age = Some(max_age);
+    self
+  }
+
+  /// Gzips the archives on a background thread, `app.log.1.gz`.
+  /// Existing uncompressed archives are left alone.
+  /// Files a crashed process could not compress are compressed on the first rotation.
+  #[cfg(feature = "compression")]
+  #[must_use]
+  pub const fn compress(mut self) -> Self {
+    self.archives.compress = true;
+    self
+  }
+
+  /// Opens the file for appending.
+  fn open(path: &Path) -> io::Result<Current> {
+    let file = OpenOptions::new().append(true).create(true).open(path)?;
+    let size = file.metadata()?.len();
+    Ok(Current { file, size })
+  }
+
+  /// Shifts the archives by one, moves the current file to the first archive and opens a new file.
+  fn rotate(&self, current: &mut Option<Current>) -> io::Result<()> {
+    if let Some(old) = current.take() {
+      _ = old.file.sync_all();
+    }
+
+    if self.archives.keep == 0 {
+      fs::remove_file(&self.archives.path)?;
+    } else {
+      self.archive_current()?;
+    }
+
+    *current = Some(Self::open(&self.archives.path)?);
+    Ok(())
+  }
+
+  /// Moves the closed current file to the first archive.
+  fn archive_current(&self) -> io::Result<()> {
+    #[cfg(feature = "compression")]
+    if self.archives.compress {
+      static PENDING: AtomicU64 = AtomicU64::new(0);
+      // Started first, so it only picks up the files that are left from before.
+      let archives = &self.archives;
+      let compressor = self.compressor.get_or_init(|| Compressor::spawn(archives.clone()));
+
+      // Moved out of the way first, so a new file can be started while it is compressed.
+      let mut pending = self.archives.path.clone().into_os_string();
+      let n = PENDING.fetch_add(1, Ordering::Relaxed);
+      pending.push(format!(".{}-{n}.pending", std::process::id()));
+      let pending = PathBuf::from(pending);
+      fs::rename(&self.archives.path, &pending)?;
+      compressor.compress(pending);
+      return Ok(());
+    }
+
+    self.archives.shift();
+    fs::rename(&self.archives.path, self.archives.archive(1))?;
+    self.archives.cleanup();
+    Ok(())
+  }
+
+  /// Writes the message, rotating first if required.
+  fn write(&self, message: &[u8]) -> io::Result<()> {
+    let mut current = self.current.lock().map_err(|_| io::Error::other("poisoned"))?;
+    self.write_locked(&mut current, message)
+  }
+
+  /// Writes the message to the locked file, rotating first if required.
+  fn write_locked(&self, current: &mut Option<Current>, message: &[u8]) -> io::Result<()> {
+    // The rotation already happened if the file could not be reopened afterwards,
+    // rotating again would shift out an archive for every message.
+    if current.is_none() {
+      *current = Some(Self::open(&self.archives.path)?);
+    }
+
+    let len = message.len() as u64;
+    let full = current.as_ref().is_some_and(|c| c.size > 0 && c.size + len > self.max_size);
+    if full {
+      self.rotate(current)?;
+    }
+
+    let Some(current) = current.as_mut() else {
+      return Err(io::Error::other("no file"));
+    };
+    current.file.write_all(message)?;
+    current.size += len;
+    Ok(())
+  }
+}
+
+impl<T: AsRef<[u8]>> Appender<T> for RollingFileAppender {
+  fn append_log_message(&self, message: &T) {
+    if self.write(message.as_ref()).is_err() {
+      self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  fn flush(&self) {
+    if let Ok(mut current) = self.current.lock() {
+      if let Some(current) = current.as_mut() {
+        _ = current.file.flush();
+      }
+    }
+  }
+
+  fn dropped(&self) -> u64 {
+    self.dropped.load(Ordering::Relaxed)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::fs;
+  #[cfg(feature = "compression")]
+  use std::fs::File;
+  #[cfg(feature = "compression")]
+  use std::io::Write;
+  #[cfg(feature = "compression")]
+  use std::path::Path;
+  use std::time::Duration;
+  #[cfg(feature = "compression")]
+  use std::time::SystemTime;
+
+  use crate::{Appender, RollingFileAppender};
+
+  #[test]
+  fn rotates() {
+    let dir = std::env::temp_dir().join(format!("trivial_log_rolling_{}", std::process::id()));
+    _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("create dir");
+    let path = dir.join("app.log");
+
+    let appender = RollingFileAppender::new(&path, 10).expect("open").keep(2);
+    for msg in ["12345\n", "67890\n", "abcde\n", "fghij\n", "klmno\n"] {
+      appender.append_log_message(&msg);
+    }
+
+    assert_eq!(fs::read_to_string(&path).expect("read"), "klmno\n");
+    assert_eq!(fs::read_to_string(dir.join("app.log.1")).expect("read"), "fghij\n");
+    assert_eq!(fs::read_to_string(dir.join("app.log.2")).expect("read"), "abcde\n");
+    assert!(!dir.join("app.log.3").exists());
+    assert_eq!(Appender::<&str>::dropped(&appender), 0);
+    _ = fs::remove_dir_all(&dir);
+  }
+
+  /// If the file cannot be reopened after a rotation the archives are left alone until it can.
+  #[test]
+  fn reopen() {
+    let dir = std::env::temp_dir().join(format!("trivial_log_reopen_{}", std::process::id()));
+    _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("create dir");
+    let path = dir.join("app.log");
+
+    let appender = RollingFileAppender::new(&path, 10).expect("open").keep(2);
+    for msg in ["12345\n", "67890\n", "abcde\n"] {
+      appender.append_log_message(&msg);
+    }
+
+    // A directory in place of the file fails the reopen like a full disk would.
+    *appender.current.lock().expect("lock") = None;
+    fs::remove_file(&path).expect("remove");
+    fs::create_dir(&path).expect("create dir");
+    for msg in ["fghij\n", "klmno\n", "pqrst\n"] {
+      appender.append_log_message(&msg);
+    }
+    assert_eq!(Appender::<&str>::dropped(&appender), 3);
+
+    fs::remove_dir(&path).expect("remove dir");
+    appender.append_log_message(&"uvwxy\n");
+    assert_eq!(fs::read_to_string(&path).expect("read"), "uvwxy\n");
+    assert_eq!(fs::read_to_string(dir.join("app.log.1")).expect("read"), "67890\n");
+    assert_eq!(fs::read_to_string(dir.join("app.log.2")).expect("read"), "12345\n");
+    _ = fs::remove_dir_all(&dir);
+  }
+
+  #[test]
+  fn cleanup() {
+    let dir = std::env::temp_dir().join(format!("trivial_log_cleanup_{}", std::process::id()));
+    _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("create dir");
+    let path = dir.join("app.log");
+
+    let appender = RollingFileAppender::new(&path, 10).expect("open").keep(5).max_total_size(12);
+    for msg in ["12345\n", "67890\n", "abcde\n", "fghij\n", "klmno\n"] {
+      appender.append_log_message(&msg);
+    }
+    assert!(dir.join("app.log.2").exists());
+    assert!(!dir.join("app.log.3").exists());
+
+    let appender = appender.max_age(Duration::ZERO);
+    appender.append_log_message(&"pqrst\n");
+    assert_eq!(fs::read_to_string(&path).expect("read"), "pqrst\n");
+    assert!(!dir.join("app.log.1").exists());
+    _ = fs::remove_dir_all(&dir);
+  }
+
+  /// Reads the gzipped file.
+  #[cfg(feature = "compression")]
+  fn gunzip(path: &Path) -> String {
+    use std::io::Read;
+
+    let mut text = String::new();
+    flate2::read::GzDecoder::new(fs::File::open(path).expect("open"))
+      .read_to_string(&mut text)
+      .expect("gunzip");
+    text
+  }
+
+  #[cfg(feature = "compression")]
+  #[test]
+  fn compress() {
+    let dir = std::env::temp_dir().join(format!("trivial_log_compress_{}", std::process::id()));
+    _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("create dir");
+    let path = dir.join("app.log");
+
+    let appender = RollingFileAppender::new(&path, 10).expect("open").keep(2).compress();
+    for msg in ["12345\n", "67890\n", "abcde\n", "fghij\n"] {
+      appender.append_log_message(&msg);
+    }
+    // Waits for the compression thread.
+    drop(appender);
+
+    assert_eq!(fs::read_to_string(&path).expect("read"), "fghij\n");
+    assert_eq!(gunzip(&dir.join("app.log.1.gz")), "abcde\n");
+    assert_eq!(gunzip(&dir.join("app.log.2.gz")), "67890\n");
+    assert_eq!(fs::read_dir(&dir).expect("list").count(), 3);
+    _ = fs::remove_dir_all(&dir);
+  }
+
+  /// A file left by a compression that did not finish is compressed on the first rotation.
+  #[cfg(feature = "compression")]
+  #[test]
+  fn stale_pending() {
+    let dir = std::env::temp_dir().join(format!("trivial_log_stale_{}", std::process::id()));
+    _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("create dir");
+    let path = dir.join("app.log");
+    let old = SystemTime::now() - Duration::from_secs(3600);
+    let write = |name: &str, modified: SystemTime| {
+      let file = File::create(dir.join(name)).expect("create");
+      (&file).write_all(b"stale\n").expect("write");
+      file.set_modified(modified).expect("set modified");
+    };
+    // Left by a process that no longer runs.
+    write("app.log.999999999-0.pending", old);
+    // Not claimed: recently modified, of this process, of another file or not created by `archive_current`.
+    let kept = [
+      "app.log.999999999-1.pending".to_string(),
+      format!("app.log.{}-0.pending", std::process::id()),
+      "other.log.999999999-0.pending".to_string(),
+      "app.log.backup.pending".to_string(),
+      "app.log.999999999-x.pending".to_string(),
+    ];
+    write("app.log.999999999-1.pending", SystemTime::now());
+    for name in kept.iter().skip(1) {
+      write(name, old);
+    }
+
+    let appender = RollingFileAppender::new(&path, 10).expect("open").keep(3).compress();
+    for msg in ["12345\n", "67890\n"] {
+      appender.append_log_message(&msg);
+    }
+    drop(appender);
+
+    assert_eq!(gunzip(&dir.join("app.log.1.gz")), "12345\n");
+    assert_eq!(gunzip(&dir.join("app.log.2.gz")), "stale\n");
+    for name in &kept {
+      assert!(dir.join(name).exists(), "{name}");
+    }
+    assert_eq!(fs::read_dir(&dir).expect("list").count(), 3 + kept.len());
+    _ = fs::remove_dir_all(&dir);
+  }
+}
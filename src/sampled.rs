@@ -0,0 +1,207 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use log::{Level, Record};
+
+use crate::{util, Appender, IntoAppender, LogContext};
+
+/// How records of a single level are sampled.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Sampling {
+  /// Forward everything
+  All,
+  /// Forward the first and then every nth record
+  OneIn(u64),
+  /// Forward a record if the next random number is below the threshold
+  Below(u64),
+}
+
+/// Appender wrapper that only forwards a sample of the records of some levels.
+///
+/// Each level can either forward 1-in-N records or a random fraction of them.
+/// Levels that are not configured are forwarded unchanged.
+/// Random numbers come from a small built-in PRNG (splitmix64), it is fast but not suitable for anything but sampling.
+pub struct Sampled<T> {
+  /// The wrapped appender
+  inner: Arc<dyn Appender<T>>,
+  /// Sampling per level
+  sampling: [Sampling; 5],
+  /// Number of records seen per level, used for `Sampling::OneIn`
+  counters: [AtomicU64; 5],
+  /// PRNG state, used for `Sampling::Below`
+  rng: AtomicU64,
+  /// Number of records that were not forwarded
+  dropped: AtomicU64,
+}
+
+impl<T> Sampled<T> {
+  /// Wraps `inner`, forwarding everything until configured otherwise.
+  #[must_use]
+  pub fn new(inner: impl IntoAppender<T>) -> Self {
+    Self {
+      inner: inner.into_appender(),
+      sampling: [Sampling::All; 5],
+      counters: [const { AtomicU64::new(0) }; 5],
+      rng: AtomicU64::new(RandomState::new().build_hasher().finish()),
+      dropped: AtomicU64::new(0),
+    }
+  }
+
+  /// Forward only the first and then every `n`th record of `level`.
+  /// `n` of 0 or 1 forwards everything.
+  #[must_use]
+  pub fn one_in(mut self, level: Level, n: u64) -> Self {
+    if let Some(sampling) = self.sampling.get_mut(util::get_idx_for_level(level)) {
+      *sampling = if n > 1 { Sampling::OneIn(n) } else { Sampling::All };
+    }
+    self
+  }
+
+  /// Forward a random `fraction` (0.0 to 1.0) of the records of `level`.
+  /// Values outside of that range are clamped, NaN forwards everything so no records are lost by accident.
+  #[must_use]
+  pub fn fraction(mut self, level: Level, fraction: f64) -> Self {
+    let fraction = if fraction.is_nan() { 1.0 } else { fraction.clamp(0.0, 1.0) };
+    #[expect(
+      clippy::cast_possible_truncation,
+      clippy::cast_sign_loss,
+      clippy::cast_precision_loss,
+      reason = "fraction is clamped to 0..=1 so the product always fits, precision does not matter for sampling"
+    )]
+    let threshold = (fraction * u64::MAX as f64) as u64;
+    if let Some(sampling) = self.sampling.get_mut(util::get_idx_for_level(level)) {
+      *sampling = if threshold == u64::MAX { Sampling::All } else { Sampling::Below(threshold) };
+    }
+    self
+  }
+
+  /// Seeds the PRNG, useful for reproducible sampling in tests.
+  #[must_use]
+  pub fn seed(self, seed: u64) -> Self {
+    self.rng.store(seed, Ordering::Relaxed);
+    self
+  }
+
+  /// Returns the next random number (splitmix64).
+  fn next_random(&self) -> u64 {
+    let mut z = self.rng.fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed);
+    z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+  }
+
+  /// Decides if a record of the given level is forwarded.
+  fn sample(&self, level: Level) -> bool {
+    let idx = util::get_idx_for_level(level);
+    match self.sampling.get(idx) {
+      None | Some(Sampling::All) => true,
+      Some(Sampling::OneIn(n)) => self
+        .counters
+        .get(idx)
+        .is_none_or(|counter| counter.fetch_add(1, Ordering::Relaxed) % n == 0),
+      Some(Sampling::Below(threshold)) => self.next_random() < *threshold,
+    }
+  }
+}
+
+impl<T> Appender<T> for Sampled<T> {
+  fn append_log_message(&self, message: &T) {
+    // Without a record there is no level to sample by.
+    self.inner.append_log_message(message);
+  }
+
+  fn append_log_record(&self, record: &Record<'_>, message: &T) {
+    if self.sample(record.level()) {
+      self.inner.append_log_record(record, message);
+    } else {
+      self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  fn append_log_context(&self, ctx: &LogContext<'_>, record: &Record<'_>, message: &T) {
+    if self.sample(record.level()) {
+      self.inner.append_log_context(ctx, record, message);
+    } else {
+      self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  fn flush(&self) {
+    self.inner.flush();
+  }
+
+  fn dump(&self, out: &mut dyn io::Write) {
+    self.inner.dump(out);
+  }
+
+  fn dropped(&self) -> u64 {
+    self.dropped.load(Ordering::Relaxed) + self.inner.dropped()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::sync::atomic::{AtomicUsize, Ordering};
+  use std::sync::Arc;
+
+  use log::{Level, Record};
+
+  use crate::{Appender, Sampled};
+
+  fn count(sampled: &Sampled<u32>, level: Level, n: usize) {
+    let record = Record::builder().level(level).build();
+    for _ in 0..n {
+      sampled.append_log_record(&record, &0);
+    }
+  }
+
+  #[test]
+  fn one_in_n() {
+    let seen = Arc::new(AtomicUsize::new(0));
+    let seen2 = Arc::clone(&seen);
+    let sampled = Sampled::new(move |_: &u32| {
+      seen2.fetch_add(1, Ordering::Relaxed);
+    })
+    .one_in(Level::Trace, 10)
+    .one_in(Level::Debug, 1);
+
+    count(&sampled, Level::Trace, 95);
+    assert_eq!(seen.swap(0, Ordering::Relaxed), 10);
+    count(&sampled, Level::Debug, 7);
+    assert_eq!(seen.swap(0, Ordering::Relaxed), 7);
+    count(&sampled, Level::Error, 3);
+    assert_eq!(seen.swap(0, Ordering::Relaxed), 3);
+    assert_eq!(sampled.dropped(), 85);
+  }
+
+  #[test]
+  fn fraction() {
+    let seen = Arc::new(AtomicUsize::new(0));
+    let seen2 = Arc::clone(&seen);
+    let sampled = Sampled::new(move |_: &u32| {
+      seen2.fetch_add(1, Ordering::Relaxed);
+    })
+    .fraction(Level::Trace, 0.25)
+    .fraction(Level::Debug, 0.0)
+    .fraction(Level::Info, 1.0)
+    .fraction(Level::Warn, f64::NAN)
+    .fraction(Level::Error, -1.0)
+    .seed(42);
+
+    count(&sampled, Level::Trace, 10_000);
+    let trace = seen.swap(0, Ordering::Relaxed);
+    assert!((2_000..3_000).contains(&trace), "{trace}");
+    count(&sampled, Level::Debug, 1_000);
+    assert_eq!(seen.swap(0, Ordering::Relaxed), 0);
+    count(&sampled, Level::Info, 1_000);
+    assert_eq!(seen.swap(0, Ordering::Relaxed), 1_000);
+    count(&sampled, Level::Warn, 1_000);
+    assert_eq!(seen.swap(0, Ordering::Relaxed), 1_000);
+    count(&sampled, Level::Error, 1_000);
+    assert_eq!(seen.swap(0, Ordering::Relaxed), 0);
+  }
+}
@@ -0,0 +1,210 @@
+use std::io::{self, BufWriter, Stderr, Stdout, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::Appender;
+
+/// Writes either directly to the stream or through a buffer.
+enum StdWriter<S: Write> {
+  /// Every message is written with a single `write_all` on the locked stream.
+  Direct(S),
+  /// Messages are collected in a buffer that is written when full or flushed.
+  Buffered(Mutex<BufWriter<S>>),
+}
+
+impl<S: Write> StdWriter<S>
+where
+  for<'a> &'a S: Write,
+{
+  /// Creates a buffered writer with the given capacity.
+  fn buffered(stream: S, capacity: usize) -> Self {
+    Self::Buffered(Mutex::new(BufWriter::with_capacity(capacity, stream)))
+  }
+
+  /// Locks the buffer, ignoring poison.
+  fn lock(buffer: &Mutex<BufWriter<S>>) -> MutexGuard<'_, BufWriter<S>> {
+    buffer.lock().unwrap_or_else(|poison| {
+      buffer.clear_poison();
+      poison.into_inner()
+    })
+  }
+
+  /// Writes the message, errors such as a closed pipe are counted in `dropped`.
+  fn write(&self, message: &[u8], dropped: &AtomicU64) {
+    let result = match self {
+      // write_all on &Stdout/&Stderr holds the lock for the entire message.
+      Self::Direct(stream) => (&mut &*stream).write_all(message),
+      Self::Buffered(buffer) => Self::lock(buffer).write_all(message),
+    };
+    if result.is_err() {
+      dropped.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+
+  /// Writes the buffer to the stream and flushes it.
+  fn flush(&self) {
+    match self {
+      Self::Direct(stream) => _ = (&mut &*stream).flush(),
+      Self::Buffered(buffer) => _ = Self::lock(buffer).flush(),
+    }
+  }
+}
+
+/// Appender that writes the formatted messages to stdout.
+///
+/// Unlike `print!` this locks stdout once per message and does not panic if stdout is closed,
+/// for example if the output is piped into `head`.
+/// `StdoutAppender::buffered` additionally collects messages in a buffer
+/// that is written when it is full and on `trivial_log::free()`.
+///
+///```rust
+/// use log::LevelFilter;
+/// use trivial_log::StdoutAppender;
+/// trivial_log::builder()
+///   .default_format(|builder| builder.appender_filter(LevelFilter::Info, StdoutAppender::buffered()))
+///   .init()
+///   .unwrap();
+/// log::info!("Hello");
+/// trivial_log::free();
+///```
+pub struct StdoutAppender {
+  /// Where the messages are written to
+  writer: StdWriter<Stdout>,
+  /// Number of messages that could not be written
+  dropped: AtomicU64,
+}
+
+impl StdoutAppender {
+  /// Creates an appender that writes every message to stdout immediately.
+  #[must_use]
+  pub fn new() -> Self {
+    Self { writer: StdWriter::Direct(io::stdout()), dropped: AtomicU64::new(0) }
+  }
+
+  /// Creates an appender that buffers up to 8 KiB of messages before writing them to stdout.
+  #[must_use]
+  pub fn buffered() -> Self {
+    Self::with_capacity(8 * 1024)
+  }
+
+  /// Creates an appender that buffers up to `capacity` bytes of messages before writing them to stdout.
+  #[must_use]
+  pub fn with_capacity(capacity: usize) -> Self {
+    Self { writer: StdWriter::buffered(io::stdout(), capacity), dropped: AtomicU64::new(0) }
+  }
+}
+
+impl Default for StdoutAppender {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<T: AsRef<[u8]>> Appender<T> for StdoutAppender {
+  fn append_log_message(&self, message: &T) {
+    self.writer.write(message.as_ref(), &self.dropped);
+  }
+
+  fn flush(&self) {
+    self.writer.flush();
+  }
+
+  fn dropped(&self) -> u64 {
+    self.dropped.load(Ordering::Relaxed)
+  }
+}
+
+/// Appender that writes the formatted messages to stderr.
+///
+/// Unlike `eprint!` this locks stderr once per message and does not panic if stderr is closed.
+/// `StderrAppender::buffered` additionally collects messages in a buffer
+/// that is written when it is full and on `trivial_log::free()`.
+pub struct StderrAppender {
+  /// Where the messages are written to
+  writer: StdWriter<Stderr>,
+  /// Number of messages that could not be written
+  dropped: AtomicU64,
+}
+
+impl StderrAppender {
+  /// Creates an appender that writes every message to stderr immediately.
+  #[must_use]
+  pub fn new() -> Self {
+    Self { writer: StdWriter::Direct(io::stderr()), dropped: AtomicU64::new(0) }
+  }
+
+  /// Creates an appender that buffers up to 8 KiB of messages before writing them to stderr.
+  #[must_use]
+  pub fn buffered() -> Self {
+    Self::with_capacity(8 * 1024)
+  }
+
+  /// Creates an appender that buffers up to `capacity` bytes of messages before writing them to stderr.
+  #[must_use]
+  pub fn with_capacity(capacity: usize) -> Self {
+    Self { writer: StdWriter::buffered(io::stderr(), capacity), dropped: AtomicU64::new(0) }
+  }
+}
+
+impl Default for StderrAppender {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<T: AsRef<[u8]>> Appender<T> for StderrAppender {
+  fn append_log_message(&self, message: &T) {
+    self.writer.write(message.as_ref(), &self.dropped);
+  }
+
+  fn flush(&self) {
+    self.writer.flush();
+  }
+
+  fn dropped(&self) -> u64 {
+    self.dropped.load(Ordering::Relaxed)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use log::{Level, LevelFilter};
+
+  use crate::{StderrAppender, StdoutAppender};
+
+  /// Logs through buffered and unbuffered appenders, started by `std_appenders`.
+  #[test]
+  #[ignore = "started in a child process by `std_appenders`"]
+  fn appender_child() {
+    crate::builder()
+      .default_format(|builder| {
+        builder
+          .appender_filter(LevelFilter::Info, StdoutAppender::buffered())
+          .appender(Level::Error, StderrAppender::new())
+      })
+      .format(
+        |_, record| Some(format!("bytes {}\n", record.args()).into_bytes()),
+        |builder| builder.appender(Level::Warn, StderrAppender::with_capacity(16)),
+      )
+      .init()
+      .expect("init");
+
+    for n in 0..100 {
+      log::info!("info {n}");
+    }
+    log::warn!("warn");
+    log::error!("error");
+    crate::free();
+  }
+
+  #[test]
+  fn std_appenders() {
+    let (out, err) = crate::child::run("std_appender::test::appender_child", &[]);
+    for n in 0..100 {
+      assert!(out.contains(&format!(" - info {n}\n")), "{out}");
+    }
+    assert!(out.contains(" - error\n"), "{out}");
+    assert!(err.contains("bytes warn\n") && err.contains(" - error\n"), "{err}");
+    assert!(!err.contains("info"), "{err}");
+  }
+}
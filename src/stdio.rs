@@ -0,0 +1,270 @@
+use std::env;
+use std::ffi::OsString;
+use std::io::IsTerminal;
+
+use log::{Level, LevelFilter};
+
+use crate::{util, DefaultFormatOptions};
+
+/// A standard output stream.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum StdStream {
+  /// stdout
+  Stdout,
+  /// stderr
+  Stderr,
+}
+
+/// Decides which levels `init_std` writes to stdout and which to stderr.
+///
+/// The default writes `Level::Error` to stderr and everything else to stdout.
+///
+///```rust
+/// use log::Level;
+/// use trivial_log::{StdSplit, StdStream};
+/// // Warn and Error to stderr
+/// let split = StdSplit::stderr_from(Level::Warn);
+/// // Only Info to stderr
+/// let split = StdSplit::all(StdStream::Stdout).level(Level::Info, StdStream::Stderr);
+///```
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct StdSplit {
+  /// The stream for each level, indexed by `util::get_idx_for_level`
+  streams: [StdStream; 5],
+}
+
+impl StdSplit {
+  /// Writes all levels to the same stream.
+  #[must_use]
+  pub const fn all(stream: StdStream) -> Self {
+    Self { streams: [stream; 5] }
+  }
+
+  /// Writes `level` and all more severe levels to stderr and the less severe levels to stdout.
+  #[must_use]
+  pub const fn stderr_from(level: Level) -> Self {
+    use StdStream::{Stderr as E, Stdout as O};
+    // Indexed Trace, Debug, Info, Warn, Error
+    let streams = match level {
+      Level::Error => [O, O, O, O, E],
+      Level::Warn => [O, O, O, E, E],
+      Level::Info => [O, O, E, E, E],
+      Level::Debug => [O, E, E, E, E],
+      Level::Trace => [E, E, E, E, E],
+    };
+
+    Self { streams }
+  }
+
+  /// Sets the stream of a single level.
+  #[must_use]
+  pub fn level(mut self, level: Level, stream: StdStream) -> Self {
+    if let Some(slot) = self.streams.get_mut(util::get_idx_for_level(level)) {
+      *slot = stream;
+    }
+
+    self
+  }
+
+  /// The stream for the level.
+  #[must_use]
+  pub fn stream(&self, level: Level) -> StdStream {
+    self.streams.get(util::get_idx_for_level(level)).copied().unwrap_or(StdStream::Stderr)
+  }
+}
+
+impl Default for StdSplit {
+  fn default() -> Self {
+    Self::stderr_from(Level::Error)
+  }
+}
+
+/// Whether the level prefix of the default format is colored when logging to stdout/stderr.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum ColorChoice {
+  /// Color if the stream is a terminal.
+  /// `NO_COLOR` disables and `CLICOLOR_FORCE` forces colors regardless of the terminal.
+  #[default]
+  Auto,
+  /// Always color.
+  Always,
+  /// Never color.
+  Never,
+}
+
+impl ColorChoice {
+  /// Decides whether to color output written to `stream`.
+  pub(crate) fn enabled(self, stream: &impl IsTerminal) -> bool {
+    match self {
+      Self::Auto => {
+        resolve(env::var_os("NO_COLOR"), env::var_os("CLICOLOR_FORCE"), || stream.is_terminal())
+      }
+      Self::Always => true,
+      Self::Never => false,
+    }
+  }
+}
+
+/// Color unless `NO_COLOR` is set, used when the stream is unknown.
+pub fn color_from_env() -> bool {
+  resolve(env::var_os("NO_COLOR"), env::var_os("CLICOLOR_FORCE"), || true)
+}
+
+/// Applies `NO_COLOR` (<https://no-color.org>) and `CLICOLOR_FORCE` before asking `is_terminal`.
+/// Both variables are ignored if they are empty, `CLICOLOR_FORCE` is also ignored if it is "0".
+fn resolve(
+  no_color: Option<OsString>,
+  force: Option<OsString>,
+  is_terminal: impl FnOnce() -> bool,
+) -> bool {
+  if no_color.is_some_and(|v| !v.is_empty()) {
+    return false;
+  }
+
+  if force.is_some_and(|v| !v.is_empty() && v != "0") {
+    return true;
+  }
+
+  is_terminal()
+}
+
+/// Options for `init_std`, `init_stdout` and `init_stderr`.
+///
+/// A `LevelFilter` converts into options that use the default format.
+///
+///```rust
+/// use trivial_log::{ColorChoice, DefaultFormatOptions, StdOptions};
+/// let options = StdOptions::new(log::LevelFilter::Trace)
+///   .format(DefaultFormatOptions::new().target(true).location(true))
+///   .color(ColorChoice::Never);
+///```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct StdOptions {
+  /// The most verbose level that is logged
+  pub(crate) level: LevelFilter,
+  /// Options of the default format
+  pub(crate) format: DefaultFormatOptions,
+  /// Whether to color the level prefix
+  pub(crate) color: ColorChoice,
+  /// Which levels go to stdout and which to stderr, only used by `init_std`
+  pub(crate) split: StdSplit,
+}
+
+impl StdOptions {
+  /// Creates options for the given level that use the default format.
+  #[must_use]
+  pub const fn new(level: LevelFilter) -> Self {
+    Self {
+      level,
+      format: DefaultFormatOptions::new(),
+      color: ColorChoice::Auto,
+      split: StdSplit::stderr_from(Level::Error),
+    }
+  }
+
+  /// Sets the options of the default format.
+  #[must_use]
+  pub fn format(mut self, format: DefaultFormatOptions) -> Self {
+    self.format = format;
+    self
+  }
+
+  /// Sets whether the level prefix is colored. Defaults to `ColorChoice::Auto`,
+  /// which colors only if the stream is a terminal and respects `NO_COLOR`/`CLICOLOR_FORCE`.
+  #[must_use]
+  pub const fn color(mut self, color: ColorChoice) -> Self {
+    self.color = color;
+    self
+  }
+
+  /// Sets which levels `init_std` writes to stdout and which to stderr.
+  /// Defaults to `Level::Error` to stderr and everything else to stdout.
+  #[must_use]
+  pub const fn split(mut self, split: StdSplit) -> Self {
+    self.split = split;
+    self
+  }
+
+  /// The format options for a stream, with color decided by `self.color`.
+  pub(crate) fn format_for(&self, stream: &impl IsTerminal) -> DefaultFormatOptions {
+    self.format.clone().color(self.color.enabled(stream))
+  }
+}
+
+impl From<LevelFilter> for StdOptions {
+  fn from(level: LevelFilter) -> Self {
+    Self::new(level)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::env;
+  use std::ffi::OsString;
+
+  use log::{Level, LevelFilter};
+
+  use crate::stdio::resolve;
+  use crate::{StdOptions, StdSplit, StdStream};
+
+  /// Env var that selects the split of `std_child`.
+  const SPLIT: &str = "TRIVIAL_LOG_STD_SPLIT";
+
+  /// Runs `std_child` in a child process and returns its (stdout, stderr).
+  fn run_child(split: &str) -> (String, String) {
+    crate::child::run("stdio::test::std_child", &[(SPLIT, split), ("NO_COLOR", "1")])
+  }
+
+  /// Logs one message per level, started by `std_split`.
+  #[test]
+  #[ignore = "started in a child process by `std_split`"]
+  fn std_child() {
+    let split = match env::var(SPLIT).as_deref() {
+      Ok("warn") => StdSplit::stderr_from(Level::Warn),
+      Ok("info_only") => StdSplit::all(StdStream::Stdout).level(Level::Info, StdStream::Stderr),
+      _ => StdSplit::default(),
+    };
+
+    crate::init_std(StdOptions::new(LevelFilter::Debug).split(split)).expect("init");
+    log::error!("msg-error");
+    log::warn!("msg-warn");
+    log::info!("msg-info");
+    log::debug!("msg-debug");
+    log::trace!("msg-trace");
+    crate::free();
+  }
+
+  #[test]
+  fn std_split() {
+    let (out, err) = run_child("default");
+    assert!(err.contains("[E] - ") && err.contains(" - msg-error\n"), "{err}");
+    for msg in ["msg-warn", "msg-info", "msg-debug"] {
+      assert!(out.contains(msg), "{out}");
+      assert!(!err.contains(msg), "{err}");
+    }
+    assert!(!out.contains("msg-error"), "{out}");
+    assert!(!out.contains("msg-trace") && !err.contains("msg-trace"));
+
+    let (out, err) = run_child("warn");
+    assert!(err.contains("msg-error") && err.contains("msg-warn"), "{err}");
+    assert!(out.contains("msg-info") && out.contains("msg-debug"), "{out}");
+    assert!(!out.contains("msg-warn") && !err.contains("msg-info"));
+
+    let (out, err) = run_child("info_only");
+    assert!(err.contains("msg-info") && !err.contains("msg-error"), "{err}");
+    assert!(out.contains("msg-error") && out.contains("msg-warn") && !out.contains("msg-info"));
+  }
+
+  #[test]
+  fn color_env() {
+    let set = |v: &str| Some(OsString::from(v));
+    assert!(resolve(None, None, || true));
+    assert!(!resolve(None, None, || false));
+    assert!(!resolve(set("1"), None, || true));
+    assert!(!resolve(set("1"), set("1"), || true));
+    assert!(resolve(set(""), None, || true));
+    assert!(resolve(None, set("1"), || false));
+    assert!(!resolve(None, set("0"), || false));
+    assert!(!resolve(None, set(""), || false));
+  }
+}
@@ -0,0 +1,118 @@
+use std::io;
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use log::{Level, Record};
+
+use crate::Appender;
+
+/// Appender that sends the formatted messages to the local syslog daemon over its unix socket.
+///
+/// Messages are sent as `<PRI>ident[pid]: message` (RFC 3164 without a timestamp, the daemon adds it),
+/// the severity is derived from the level of the record and a trailing newline is removed.
+/// Usually combined with a format that neither prints a timestamp nor the level.
+pub struct SyslogAppender {
+  /// Socket connected to the daemon
+  socket: UnixDatagram,
+  /// Name of the program, prepended to every message
+  ident: String,
+  /// Syslog facility code, 1 (user) by default
+  facility: u8,
+  /// Number of messages that could not be sent
+  dropped: AtomicU64,
+}
+
+impl SyslogAppender {
+  /// Connects to the syslog daemon at `/dev/log`.
+  /// The ident is the file name of the executable.
+  /// # Errors
+  /// If the socket cannot be connected, for example because no daemon is running.
+  pub fn new() -> io::Result<Self> {
+    Self::with_path("/dev/log")
+  }
+
+  /// Connects to the syslog daemon listening on the unix datagram socket at `path`.
+  /// # Errors
+  /// If the socket cannot be connected.
+  pub fn with_path(path: impl AsRef<Path>) -> io::Result<Self> {
+    let socket = UnixDatagram::unbound()?;
+    socket.connect(path)?;
+    let ident = std::env::current_exe()
+      .ok()
+      .and_then(|exe| exe.file_name().map(|name| name.to_string_lossy().into_owned()))
+      .unwrap_or_else(|| "trivial_log".to_string());
+    Ok(Self { socket, ident, facility: 1, dropped: AtomicU64::new(0) })
+  }
+
+  /// Sets the name of the program that is prepended to every message.
+  #[must_use]
+  pub fn ident(mut self, ident: impl Into<String>) -> Self {
+    self.ident = ident.into();
+    self
+  }
+
+  /// Sets the facility code, for example 3 for daemon or 16 to 23 for local0 to local7. Defaults to 1 (user).
+  #[must_use]
+  pub const fn facility(mut self, facility: u8) -> Self {
+    self.facility = facility;
+    self
+  }
+
+  /// Sends the message with the given severity.
+  fn send(&self, severity: u8, message: &[u8]) {
+    let message = message.strip_suffix(b"\n").unwrap_or(message);
+    let priority = u16::from(self.facility) * 8 + u16::from(severity);
+    let mut datagram = format!("<{priority}>{}[{}]: ", self.ident, std::process::id()).into_bytes();
+    datagram.extend_from_slice(message);
+    if self.socket.send(&datagram).is_err() {
+      self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+  }
+}
+
+impl<T: AsRef<[u8]>> Appender<T> for SyslogAppender {
+  fn append_log_message(&self, message: &T) {
+    // Without a record the level is unknown, notice is between info and warning.
+    self.send(5, message.as_ref());
+  }
+
+  fn append_log_record(&self, record: &Record<'_>, message: &T) {
+    let severity = match record.level() {
+      Level::Error => 3,
+      Level::Warn => 4,
+      Level::Info => 6,
+      Level::Debug | Level::Trace => 7,
+    };
+    self.send(severity, message.as_ref());
+  }
+
+  fn dropped(&self) -> u64 {
+    self.dropped.load(Ordering::Relaxed)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::os::unix::net::UnixDatagram;
+
+  use log::{Level, Record};
+
+  use crate::{Appender, SyslogAppender};
+
+  #[test]
+  fn send() {
+    let path = std::env::temp_dir().join(format!("trivial_log_syslog_{}.sock", std::process::id()));
+    _ = std::fs::remove_file(&path);
+    let daemon = UnixDatagram::bind(&path).expect("bind");
+
+    let appender = SyslogAppender::with_path(&path).expect("connect").ident("app").facility(16);
+    appender.append_log_record(&Record::builder().level(Level::Warn).build(), &"disk full\n");
+
+    let mut buf = [0; 128];
+    let len = daemon.recv(&mut buf).expect("recv");
+    let expected = format!("<132>app[{}]: disk full", std::process::id());
+    assert_eq!(buf.get(..len), Some(expected.as_bytes()));
+    _ = std::fs::remove_file(&path);
+  }
+}
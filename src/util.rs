@@ -1,7 +1,7 @@
 use std::sync::OnceLock;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::SystemTime;
 
-use crate::Error;
+use crate::{Appender, DefaultFormatOptions, Error, LogContext};
 use crate::{Handler, Level, LevelFilter, TL};
 use log::Record;
 
@@ -36,43 +36,67 @@ pub const fn get_idx_for_level(level: Level) -> usize {
 
 /// The default log message format used.
 pub fn default_format(now: SystemTime, record: &Record<'_>) -> Option<String> {
-  use std::fmt::Write;
-  let prefix = match record.metadata().level() {
-    Level::Error => "[E]",
-    Level::Warn => "[W]",
-    Level::Info => "[I]",
-    Level::Debug => "[D]",
-    Level::Trace => "[T]",
-  };
-
-  let mut buf = String::with_capacity(128);
-  let instant = now.duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
-
-  #[cfg(feature = "chrono")]
-  let instant = {
-    /// Unambiguous date format with Month names
-    const FORMAT: chrono::format::StrftimeItems<'_> =
-      chrono::format::StrftimeItems::new("%d %b %Y %H:%M:%S%.3f UTC");
-    chrono::DateTime::from_timestamp_millis(i64::try_from(instant).unwrap_or(0))
-      .map(|dt| dt.format_with_items(FORMAT))?
-  };
-
-  if writeln!(
-    buf,
-    "{} - {} - {:?} - {}",
-    prefix,
-    instant,
-    std::thread::current().id(),
-    record.args()
-  )
-  .is_ok()
-  {
-    return Some(buf);
+  DefaultFormatOptions::DEFAULT.format(now, record)
+}
+
+/// Returns the hostname of the machine.
+pub fn hostname() -> Option<String> {
+  #[cfg(target_os = "linux")]
+  if let Ok(hostname) = std::fs::read_to_string("/proc/sys/kernel/hostname") {
+    let hostname = hostname.trim();
+    if !hostname.is_empty() {
+      return Some(hostname.to_string());
+    }
+  }
+
+  std::env::var("HOSTNAME")
+    .or_else(|_| std::env::var("COMPUTERNAME"))
+    .ok()
+    .filter(|name| !name.is_empty())
+}
+
+/// Returns the kernel thread id of the current thread (`gettid`), cached per thread.
+/// It is read from the `/proc/thread-self` symlink which points to `<pid>/task/<tid>`.
+#[cfg(target_os = "linux")]
+pub fn os_thread_id() -> Option<u64> {
+  use std::cell::Cell;
+
+  thread_local! {
+    /// The cached thread id
+    static TID: Cell<Option<u64>> = const { Cell::new(None) };
   }
 
+  TID.with(|cached| {
+    if let Some(tid) = cached.get() {
+      return Some(tid);
+    }
+
+    let link = std::fs::read_link("/proc/thread-self").ok()?;
+    let tid = link.file_name()?.to_str()?.parse().ok()?;
+    cached.set(Some(tid));
+    Some(tid)
+  })
+}
+
+/// Returns the kernel thread id of the current thread, only supported on Linux.
+#[cfg(not(target_os = "linux"))]
+pub const fn os_thread_id() -> Option<u64> {
   None
 }
 
+/// Forwards a message to `append_log_context` if the context is known and to `append_log_record` otherwise.
+pub fn forward<T>(
+  appender: &dyn Appender<T>,
+  ctx: Option<&LogContext<'_>>,
+  record: &Record<'_>,
+  message: &T,
+) {
+  match ctx {
+    Some(ctx) => appender.append_log_context(ctx, record, message),
+    None => appender.append_log_record(record, message),
+  }
+}
+
 /// Returns the greatest `log::LevelFilter` possible that will still service all handlers fully.
 pub fn get_level_for_handlers(handlers: &Vec<Box<dyn Handler>>) -> LevelFilter {
   let mut level = LevelFilter::Off;
@@ -104,7 +128,7 @@ mod test {
       }
     }
     impl Handler for FakeLevelHandler {
-      fn log(&self, _now: std::time::SystemTime, _record: &log::Record<'_>) {}
+      fn log(&self, _ctx: &crate::LogContext<'_>, _record: &log::Record<'_>) {}
 
       fn is_enabled(&self, level: log::Level) -> bool {
         level <= self.lf
@@ -118,7 +142,7 @@ mod test {
       }
     }
     impl Handler for OnlyDebug {
-      fn log(&self, _now: std::time::SystemTime, _record: &log::Record<'_>) {}
+      fn log(&self, _ctx: &crate::LogContext<'_>, _record: &log::Record<'_>) {}
 
       fn is_enabled(&self, level: log::Level) -> bool {
         level == log::Level::Debug
@@ -151,4 +175,15 @@ mod test {
     ];
     assert_eq!(get_level_for_handlers(&handlers), LevelFilter::Debug);
   }
+
+  #[cfg(target_os = "linux")]
+  #[test]
+  fn os_thread_id() {
+    use crate::util::os_thread_id;
+
+    let tid = os_thread_id().expect("no /proc/thread-self");
+    assert_eq!(os_thread_id(), Some(tid));
+    let other = std::thread::spawn(os_thread_id).join().expect("thread panicked");
+    assert!(other.is_some_and(|other| other != tid));
+  }
 }
@@ -0,0 +1,188 @@
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use crate::{config, Error};
+
+/// Watcher threads that are running, stopped by `trivial_log::free()`.
+static THREADS: Mutex<Vec<(Arc<Shared>, JoinHandle<()>)>> = Mutex::new(Vec::new());
+
+/// Identifies a version of the file by modification time, size and a hash of its contents,
+/// the modification time alone may be too coarse.
+type Version = (SystemTime, u64, u64);
+
+/// State shared between the watcher and its thread.
+struct Shared {
+  /// The configuration file
+  path: PathBuf,
+  /// Version of the file that was last loaded, successfully or not
+  loaded: Mutex<Option<Version>>,
+  /// Set to stop the thread
+  stopped: Mutex<bool>,
+  /// Wakes the thread when it is stopped
+  wake: Condvar,
+}
+
+impl Shared {
+  /// Loads and applies the file if it changed since it was last loaded.
+  fn reload(&self) -> Result<bool, Error> {
+    let read = || -> io::Result<(Version, String)> {
+      let modified = fs::metadata(&self.path)?.modified()?;
+      let text = fs::read_to_string(&self.path)?;
+      let mut hasher = DefaultHasher::new();
+      text.hash(&mut hasher);
+      Ok(((modified, text.len() as u64, hasher.finish()), text))
+    };
+    let (version, text) = read().map_err(|err| Error::Config {
+      line: None,
+      message: format!("cannot read {}: {err}", self.path.display()),
+    })?;
+
+    // A broken file is reported once and not retried until it changes again.
+    if self.loaded().replace(version) == Some(version) {
+      return Ok(false);
+    }
+
+    let config = config::parse_file(&self.path, &text)?;
+    // A concurrent reload that saw a newer version wins.
+    if *self.loaded() != Some(version) {
+      return Ok(false);
+    }
+    config.init()?;
+    Ok(true)
+  }
+
+  /// Locks the version that was last loaded.
+  fn loaded(&self) -> MutexGuard<'_, Option<Version>> {
+    self.loaded.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  /// Polls the file every `interval` until stopped.
+  fn run(&self, interval: Duration) {
+    while !self.wait(interval) {
+      if let Err(err) = self.reload() {
+        log::error!(target: "trivial_log::config", "keeping the previous configuration: {err}");
+      }
+    }
+  }
+
+  /// Waits for `interval` unless stopped, returns whether the thread was stopped.
+  fn wait(&self, interval: Duration) -> bool {
+    let stopped = self.stopped();
+    if *stopped {
+      return true;
+    }
+    let (stopped, _) =
+      self.wake.wait_timeout(stopped, interval).unwrap_or_else(PoisonError::into_inner);
+    *stopped
+  }
+
+  /// Locks the stop flag.
+  fn stopped(&self) -> MutexGuard<'_, bool> {
+    self.stopped.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+}
+
+/// Stops and joins the watcher threads, all of them if `only` is `None`.
+fn stop_threads(only: Option<&Arc<Shared>>) {
+  let threads: Vec<_> = {
+    let mut threads = THREADS.lock().unwrap_or_else(PoisonError::into_inner);
+    let (stop, keep) =
+      threads.drain(..).partition(|(shared, _)| only.is_none_or(|only| Arc::ptr_eq(only, shared)));
+    *threads = keep;
+    stop
+  };
+
+  for (shared, handle) in threads {
+    *shared.stopped() = true;
+    shared.wake.notify_all();
+    // A watcher that frees the logger from its own thread cannot join itself.
+    if handle.thread().id() != thread::current().id() {
+      _ = handle.join();
+    }
+  }
+}
+
+/// Stops all watcher threads, called by `trivial_log::free()`.
+pub fn stop_all() {
+  stop_threads(None);
+}
+
+/// Reloads the logger when its configuration file changes.
+///
+/// The file is loaded with `config::load` and applied with `Config::init` when the watcher is created
+/// and whenever its modification time, size or contents changed since it was last loaded,
+/// either on demand with `reload` or by a background thread started with `watch`.
+/// If the changed file is invalid the previous configuration stays active.
+///
+/// The background thread keeps running when the watcher is dropped,
+/// it is stopped by `stop` or `trivial_log::free()`.
+///
+///```rust
+/// use std::time::Duration;
+/// use trivial_log::config::ConfigWatcher;
+///
+/// let path = std::env::temp_dir().join("trivial_log_watch_doc.conf");
+/// std::fs::write(&path, "[appender.console]\nkind = stdout\n").unwrap();
+/// let watcher = ConfigWatcher::new(&path).unwrap().watch(Duration::from_secs(5));
+/// log::info!("reconfigured whenever the file changes");
+/// trivial_log::free();
+///```
+pub struct ConfigWatcher {
+  /// State shared with the thread
+  shared: Arc<Shared>,
+}
+
+impl ConfigWatcher {
+  /// Loads and applies the configuration file.
+  /// # Errors
+  /// If the file cannot be read, is invalid or cannot be applied.
+  pub fn new(path: impl AsRef<Path>) -> Result<Self, Error> {
+    let shared = Arc::new(Shared {
+      path: path.as_ref().to_path_buf(),
+      loaded: Mutex::new(None),
+      stopped: Mutex::new(false),
+      wake: Condvar::new(),
+    });
+    shared.reload()?;
+    Ok(Self { shared })
+  }
+
+  /// Starts a thread that checks the file for changes every `interval`.
+  /// Errors are logged with the target `trivial_log::config` using the previous configuration.
+  #[must_use]
+  pub fn watch(self, interval: Duration) -> Self {
+    self.stop();
+    *self.shared.stopped() = false;
+
+    let shared = Arc::clone(&self.shared);
+    let spawned = thread::Builder::new()
+      .name("trivial_log-config".to_string())
+      .spawn(move || shared.run(interval));
+    if let Ok(handle) = spawned {
+      THREADS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push((Arc::clone(&self.shared), handle));
+    }
+    self
+  }
+
+  /// Loads and applies the file if it changed since it was last loaded.
+  /// Returns whether the configuration was replaced.
+  /// # Errors
+  /// If the file cannot be read, is invalid or cannot be applied, the previous configuration stays active.
+  /// An invalid file is only reported once, until it changes again.
+  pub fn reload(&self) -> Result<bool, Error> {
+    self.shared.reload()
+  }
+
+  /// Stops the thread started by `watch`, if any.
+  pub fn stop(&self) {
+    stop_threads(Some(&self.shared));
+  }
+}
@@ -0,0 +1,102 @@
+//! Checks that the default format does not allocate per record once the thread local buffer is warm.
+#![expect(unsafe_code, reason = "a counting global allocator requires unsafe impl GlobalAlloc")]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use log::{Level, LevelFilter};
+use trivial_log::DefaultFormatOptions;
+
+/// Forwards to the system allocator and counts allocations of threads that enabled counting.
+struct CountingAlloc;
+
+/// Allocations made while counting was enabled.
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+  /// Whether allocations of the current thread are counted.
+  static COUNTING: Cell<bool> = const { Cell::new(false) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    if COUNTING.try_with(Cell::get).unwrap_or(false) {
+      ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    }
+    unsafe { System.alloc(layout) }
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    unsafe { System.dealloc(ptr, layout) }
+  }
+
+  unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+    if COUNTING.try_with(Cell::get).unwrap_or(false) {
+      ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    }
+    unsafe { System.realloc(ptr, layout, new_size) }
+  }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Returns the number of allocations made by the current thread while running `func`.
+fn allocations(func: impl FnOnce()) -> usize {
+  let before = ALLOCATIONS.load(Ordering::Relaxed);
+  COUNTING.set(true);
+  func();
+  COUNTING.set(false);
+  ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+/// Logs a few records of every kind the test checks.
+fn log_records(n: usize) {
+  for i in 0..n {
+    log::info!("message {i} of {n}");
+    log::error!(target: "other", "error {}", i * 2);
+    // Disabled
+    log::trace!("trace {i}");
+  }
+}
+
+#[test]
+fn default_format_does_not_allocate() {
+  let bytes = Arc::new(AtomicUsize::new(0));
+  let sink = Arc::clone(&bytes);
+  let options = DefaultFormatOptions::new().target(true).location(true);
+  trivial_log::builder()
+    .global_field("service", "alloc")
+    .pid_field()
+    .default_format(|builder| {
+      let sink = Arc::clone(&sink);
+      builder.appender_filter(LevelFilter::Debug, move |msg: &String| {
+        sink.fetch_add(msg.len(), Ordering::Relaxed);
+      })
+    })
+    .default_format_with(options, |builder| {
+      builder.appender(Level::Error, move |msg: &String| {
+        sink.fetch_add(msg.len(), Ordering::Relaxed);
+      })
+    })
+    .init()
+    .expect("init");
+
+  // Grows the buffer and initializes the thread handle.
+  log_records(10);
+  assert_eq!(allocations(|| log_records(1000)), 0);
+  assert!(bytes.load(Ordering::Relaxed) > 0);
+
+  // The counter works: the owned format allocates a String per record.
+  let record = log::Record::builder().args(format_args!("owned")).build();
+  let allocs = allocations(|| {
+    for _ in 0..100 {
+      drop(DefaultFormatOptions::new().format(std::time::SystemTime::now(), &record));
+    }
+  });
+  assert!(allocs >= 100, "{allocs}");
+
+  trivial_log::free();
+}
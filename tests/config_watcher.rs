@@ -0,0 +1,79 @@
+//! Checks that `ConfigWatcher` applies changes of the configuration file and keeps the old one on errors.
+use std::fs;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use trivial_log::config::ConfigWatcher;
+
+/// Writes a configuration that logs the message only to `log`, `padding` changes the size of the file.
+fn write_config(config: &Path, log: &Path, padding: &str) {
+  let text = format!(
+    "# {padding}\n[appender.file]\nkind = file\npath = {}\nformat = pattern\npattern = \"{{message}}\"\n",
+    log.display()
+  );
+  fs::write(config, text).expect("write config");
+}
+
+fn read(path: &Path) -> String {
+  fs::read_to_string(path).unwrap_or_default()
+}
+
+#[test]
+fn reload_on_change() {
+  let dir = std::env::temp_dir().join(format!("trivial_log_watch_{}", std::process::id()));
+  _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).expect("create dir");
+  let config = dir.join("app.conf");
+  let (first, second, third) =
+    (dir.join("first.log"), dir.join("second.log"), dir.join("third.log"));
+  let same_size = dir.join("second.txt");
+
+  write_config(&config, &first, "");
+  let watcher = ConfigWatcher::new(&config).expect("load");
+  log::info!("one");
+  assert!(!watcher.reload().expect("unchanged"));
+
+  write_config(&config, &second, "changed");
+  assert!(watcher.reload().expect("changed"));
+  log::info!("two");
+
+  // An edit that keeps the size and modification time is noticed by the contents.
+  let modified = fs::metadata(&config).and_then(|metadata| metadata.modified()).expect("mtime");
+  write_config(&config, &same_size, "changed");
+  fs::File::options()
+    .write(true)
+    .open(&config)
+    .and_then(|file| file.set_modified(modified))
+    .expect("restore mtime");
+  assert!(watcher.reload().expect("same size"));
+
+  // An invalid file is reported once and the previous configuration stays active.
+  fs::write(&config, "[appender.file]\nkind = carrier_pigeon\n").expect("write config");
+  assert!(watcher.reload().is_err());
+  assert!(!watcher.reload().expect("reported once"));
+  log::info!("three");
+
+  let watcher = watcher.watch(Duration::from_millis(10));
+  write_config(&config, &third, "watched");
+  let deadline = Instant::now() + Duration::from_secs(10);
+  while !read(&third).contains("four") {
+    assert!(Instant::now() < deadline, "configuration was not reloaded");
+    log::info!("four");
+    thread::sleep(Duration::from_millis(10));
+  }
+
+  // The thread is stopped, later changes are not applied anymore.
+  trivial_log::free();
+  write_config(&config, &first, "after free");
+  thread::sleep(Duration::from_millis(100));
+  log::info!("five");
+  assert!(!read(&first).contains("five"));
+
+  assert_eq!(read(&first), "one\n");
+  // "four" may have been logged before the thread noticed the change.
+  assert_eq!(read(&second), "two\n");
+  assert!(read(&same_size).starts_with("three\n"));
+  drop(watcher);
+  _ = fs::remove_dir_all(&dir);
+}